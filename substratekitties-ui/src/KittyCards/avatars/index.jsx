@@ -84,19 +84,26 @@ const IMAGES = {
 };
 
 
+// Mirrors `runtime/src/genetics.rs`: the dominant allele of each gene slot
+// sits at the start of the dna, in this order, and is the one a kitty expresses.
+const GENE_SLOTS = ['body', 'eyes', 'accessory', 'fur', 'mouth'];
+const GENE_IMAGES = {
+    body: IMAGES.body,
+    eyes: IMAGES.eyes,
+    accessory: IMAGES.accessories,
+    fur: IMAGES.fur,
+    mouth: IMAGES.mouth
+};
+
 function dnaToAttributes(dna) {
-    let attribute = (index, options) => {
-        return dna[index] % options
-    };
+    let attributes = {};
+    GENE_SLOTS.forEach((slot, index) => {
+        let options = GENE_IMAGES[slot];
+        attributes[slot] = options[dna[index] % options.length];
+    });
 
-    return {
-        body: IMAGES.body[attribute(0, 15)],
-        eyes: IMAGES.eyes[attribute(1, 15)],
-        accessory: IMAGES.accessories[attribute(2, 20)],
-        fur: IMAGES.fur[attribute(3, 10)],
-        mouth: IMAGES.mouth[attribute(4, 10)]
-    }
-} 
+    return attributes;
+}
 
 export function KittyAvatar(props) {
     let outerStyle = {height: "150px", position: 'relative', width: "50%"},
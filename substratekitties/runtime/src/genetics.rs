@@ -0,0 +1,215 @@
+//! Kitty genetics.
+//!
+//! A kitty's `dna` hash is read as a set of gene slots. Every slot carries a dominant allele,
+//! which is the one the kitty expresses, and a recessive allele, which it only passes on.
+//! Dominant alleles live in the first bytes of the dna, in the same order the UI reads them
+//! (body, eyes, accessory, fur, mouth), recessive alleles start at `RECESSIVE_OFFSET`.
+//! The remaining bytes carry no trait and are mixed from both parents.
+
+use runtime_primitives::Permill;
+
+/// Number of gene slots encoded in a dna hash.
+pub const GENE_SLOTS: usize = 5;
+
+/// Byte offset of the first recessive allele.
+pub const RECESSIVE_OFFSET: usize = 16;
+
+/// Alleles with this bit set dominate alleles without it.
+const DOMINANCE_BIT: u8 = 0x80;
+
+/// Number of random bytes consumed per gene slot while breeding.
+const RANDOM_BYTES_PER_SLOT: usize = 4;
+
+/// A named trait of a kitty.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum GeneSlot {
+    Body,
+    Eyes,
+    Accessory,
+    Fur,
+    Mouth,
+}
+
+impl GeneSlot {
+    /// All slots, in dna order.
+    pub const ALL: [GeneSlot; GENE_SLOTS] = [
+        GeneSlot::Body,
+        GeneSlot::Eyes,
+        GeneSlot::Accessory,
+        GeneSlot::Fur,
+        GeneSlot::Mouth,
+    ];
+
+    /// Number of distinct variants this trait can take.
+    pub fn variants(self) -> u8 {
+        match self {
+            GeneSlot::Body => 15,
+            GeneSlot::Eyes => 15,
+            GeneSlot::Accessory => 20,
+            GeneSlot::Fur => 10,
+            GeneSlot::Mouth => 10,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The pair of alleles held in one gene slot.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Gene {
+    pub dominant: u8,
+    pub recessive: u8,
+}
+
+impl Gene {
+    /// Builds a gene from two inherited alleles, letting the dominant one be expressed.
+    /// `coin` breaks the tie when both or neither allele is dominant.
+    fn from_alleles(a: u8, b: u8, coin: bool) -> Self {
+        let (dominant, recessive) = match (a & DOMINANCE_BIT != 0, b & DOMINANCE_BIT != 0) {
+            (true, false) => (a, b),
+            (false, true) => (b, a),
+            _ if coin => (a, b),
+            _ => (b, a),
+        };
+        Gene { dominant, recessive }
+    }
+}
+
+/// A decoded dna hash.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Genome {
+    pub genes: [Gene; GENE_SLOTS],
+}
+
+impl Genome {
+    /// Reads the gene slots out of a dna hash. Missing bytes read as zero.
+    pub fn decode(dna: &[u8]) -> Self {
+        let byte = |i: usize| dna.get(i).cloned().unwrap_or_default();
+        let mut genome = Genome::default();
+        for (i, gene) in genome.genes.iter_mut().enumerate() {
+            gene.dominant = byte(i);
+            gene.recessive = byte(RECESSIVE_OFFSET + i);
+        }
+        genome
+    }
+
+    /// Writes the gene slots back into a dna hash, leaving the other bytes untouched.
+    pub fn encode_into(&self, dna: &mut [u8]) {
+        for (i, gene) in self.genes.iter().enumerate() {
+            if let Some(b) = dna.get_mut(i) {
+                *b = gene.dominant;
+            }
+            if let Some(b) = dna.get_mut(RECESSIVE_OFFSET + i) {
+                *b = gene.recessive;
+            }
+        }
+    }
+
+    /// The gene held in `slot`.
+    pub fn gene(&self, slot: GeneSlot) -> Gene {
+        self.genes[slot.index()]
+    }
+
+    /// The variant of `slot` this kitty expresses.
+    pub fn trait_of(&self, slot: GeneSlot) -> u8 {
+        self.gene(slot).dominant % slot.variants()
+    }
+}
+
+/// Breeds two dna hashes into `child`.
+///
+/// For every slot the child inherits one allele from each parent, each picked at random
+/// between the parent's dominant and recessive allele. With probability `mutation_rate` one
+/// of the inherited alleles is replaced by a random one. Bytes outside the gene slots are
+/// copied from either parent at random.
+pub fn inherit(matron: &[u8], sire: &[u8], random: &[u8], mutation_rate: Permill, child: &mut [u8]) {
+    let rand = |i: usize| random.get(i % random.len().max(1)).cloned().unwrap_or_default();
+
+    for (i, byte) in child.iter_mut().enumerate() {
+        let parent = if rand(i) & 0x80 != 0 { sire } else { matron };
+        *byte = parent.get(i).cloned().unwrap_or_default();
+    }
+
+    let matron_genome = Genome::decode(matron);
+    let sire_genome = Genome::decode(sire);
+    let mutation_threshold = mutation_rate * 65_536u64;
+
+    let mut genome = Genome::default();
+    for slot in GeneSlot::ALL.iter() {
+        let i = slot.index();
+        let r = i * RANDOM_BYTES_PER_SLOT;
+        let choices = rand(r);
+
+        let pick = |gene: Gene, bit: u8| if choices & bit != 0 { gene.recessive } else { gene.dominant };
+        let mut from_matron = pick(matron_genome.gene(*slot), 0b001);
+        let mut from_sire = pick(sire_genome.gene(*slot), 0b010);
+
+        let roll = u64::from(rand(r + 1)) << 8 | u64::from(rand(r + 2));
+        if roll < mutation_threshold {
+            if choices & 0b1000 != 0 {
+                from_sire = rand(r + 3);
+            } else {
+                from_matron = rand(r + 3);
+            }
+        }
+
+        genome.genes[i] = Gene::from_alleles(from_matron, from_sire, choices & 0b100 != 0);
+    }
+
+    genome.encode_into(child);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_reads_dominant_and_recessive_alleles() {
+        let mut dna = [0u8; 32];
+        dna[0] = 17;
+        dna[RECESSIVE_OFFSET] = 3;
+
+        let genome = Genome::decode(&dna);
+        assert_eq!(genome.gene(GeneSlot::Body), Gene { dominant: 17, recessive: 3 });
+        assert_eq!(genome.trait_of(GeneSlot::Body), 2);
+    }
+
+    #[test]
+    fn dominant_allele_is_expressed() {
+        assert_eq!(Gene::from_alleles(0x01, 0x81, true), Gene { dominant: 0x81, recessive: 0x01 });
+        assert_eq!(Gene::from_alleles(0x81, 0x01, false), Gene { dominant: 0x81, recessive: 0x01 });
+    }
+
+    #[test]
+    fn child_only_carries_parent_alleles_without_mutation() {
+        let matron = [0x11u8; 32];
+        let sire = [0x22u8; 32];
+        let random = [0xa5u8; 32];
+        let mut child = [0u8; 32];
+
+        inherit(&matron, &sire, &random, Permill::zero(), &mut child);
+
+        assert!(child.iter().all(|b| *b == 0x11 || *b == 0x22));
+    }
+
+    #[test]
+    fn certain_mutation_introduces_random_alleles() {
+        let matron = [0x11u8; 32];
+        let sire = [0x22u8; 32];
+        let random = [0x33u8; 32];
+        let mut child = [0u8; 32];
+
+        inherit(&matron, &sire, &random, Permill::one(), &mut child);
+
+        let genome = Genome::decode(&child);
+        assert!(GeneSlot::ALL.iter().all(|slot| {
+            let gene = genome.gene(*slot);
+            gene.dominant == 0x33 || gene.recessive == 0x33
+        }));
+    }
+}
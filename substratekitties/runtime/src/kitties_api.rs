@@ -0,0 +1,53 @@
+//! Runtime APIs that let clients read `substratekitties` state with a single call, and the one
+//! `--dev-seal` builds blocks with.
+
+use rstd::prelude::*;
+use client::decl_runtime_apis;
+use parity_codec::Codec;
+use crate::substratekitties::{Auction, BreedQuote, KittyInfo};
+
+decl_runtime_apis! {
+	/// Enumerates open auctions without scanning the expiry buckets.
+	pub trait KittyAuctionsApi<AccountId, Hash, Balance, BlockNumber> where
+		AccountId: Codec,
+		Hash: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// All open auctions, including expired ones that still wait for settlement.
+		fn active_auctions() -> Vec<Auction<Hash, Balance, BlockNumber, AccountId>>;
+		/// The open auctions of `seller`.
+		fn auctions_by_seller(seller: AccountId) -> Vec<Auction<Hash, Balance, BlockNumber, AccountId>>;
+		/// The kitties `bidder` has a bid reserved for, with the reserved amount.
+		fn auctions_by_bidder(bidder: AccountId) -> Vec<(Hash, Balance)>;
+		/// Where each open auction is scheduled to settle, as (kitty_id, expiry bucket, index).
+		fn auction_slots() -> Vec<(Hash, BlockNumber, u32)>;
+		/// The number of auctions scheduled in the expiry bucket of `block`.
+		fn auctions_expiring_count(block: BlockNumber) -> u32;
+	}
+
+	/// Reads kitties and quotes breeding without decoding storage keys.
+	pub trait KittiesApi<AccountId, Hash, Balance, BlockNumber> where
+		AccountId: Codec,
+		Hash: Codec,
+		Balance: Codec,
+		BlockNumber: Codec,
+	{
+		/// A kitty with its owner and breeding state.
+		fn kitty(id: Hash) -> Option<KittyInfo<AccountId, Hash, Balance, BlockNumber>>;
+		/// Up to `count` ids of `owner`'s kitties from index `start`, and the number it owns.
+		fn kitties_owned_by(owner: AccountId, start: u64, count: u64) -> (Vec<Hash>, u64);
+		/// Up to `count` kitty ids from index `start`, and the number of kitties.
+		fn all_kitties(start: u64, count: u64) -> (Vec<Hash>, u64);
+		/// The auction a kitty is sold in, on its own or as part of a lot.
+		fn auction(id: Hash) -> Option<Auction<Hash, Balance, BlockNumber, AccountId>>;
+		/// What breeding `matron` with `sire` would result in at this block.
+		fn quote_breed(matron: Hash, sire: Hash) -> BreedQuote<Balance, BlockNumber>;
+	}
+
+	/// What `--dev-seal` needs to know to author the next block without Aura.
+	pub trait DevSealApi {
+		/// `Timestamp::now()` at this block, in seconds.
+		fn timestamp() -> u64;
+	}
+}
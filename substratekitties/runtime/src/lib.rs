@@ -32,6 +32,9 @@ pub use balances::Call as BalancesCall;
 pub use runtime_primitives::{Permill, Perbill};
 pub use timestamp::BlockPeriod;
 pub use support::{StorageValue, construct_runtime};
+pub use substratekitties::{Kitty, KittyInfo, Auction, AuctionKind, BreedQuote};
+#[cfg(feature = "std")]
+pub use substratekitties::balance_string;
 
 /// The type that is used for identifying authorities.
 pub type AuthorityId = <AuthoritySignature as Verify>::Signer;
@@ -48,6 +51,9 @@ pub type AccountSignature = sr25519::Signature;
 /// A hash of some data used by the chain.
 pub type Hash = primitives::H256;
 
+/// The type for recording an account's balance.
+pub type Balance = u128;
+
 /// Index of a block number in the chain.
 pub type BlockNumber = u64;
 
@@ -59,6 +65,12 @@ mod template;
 
 mod substratekitties;
 
+/// Runtime APIs exposed by `substratekitties`.
+pub mod kitties_api;
+
+/// Dna decoding and inheritance rules used by `substratekitties`.
+mod genetics;
+
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
 /// of data like extrinsics, allowing for them to continue syncing the network through upgrades
@@ -169,7 +181,7 @@ impl timestamp::Trait for Runtime {
 
 impl balances::Trait for Runtime {
 	/// The type for recording an account's balance.
-	type Balance = u128;
+	type Balance = Balance;
 	/// What to do if an account's free balance gets zeroed.
 	type OnFreeBalanceZero = ();
 	/// What to do if a new account is created.
@@ -213,7 +225,7 @@ construct_runtime!(
 		// Used for the module template in `./template.rs`
 		TemplateModule: template::{Module, Call, Storage, Event<T>},
 		
-		Substratekitties: substratekitties::{Module, Call, Storage, Event<T>},
+		Substratekitties: substratekitties::{Module, Call, Storage, Event<T>, Config<T>},
 	}
 );
 
@@ -305,4 +317,54 @@ impl_runtime_apis! {
 			Consensus::authorities()
 		}
 	}
+
+	impl kitties_api::KittyAuctionsApi<Block, AccountId, Hash, Balance, BlockNumber> for Runtime {
+		fn active_auctions() -> Vec<Auction<Hash, Balance, BlockNumber, AccountId>> {
+			Substratekitties::active_auctions()
+		}
+
+		fn auctions_by_seller(seller: AccountId) -> Vec<Auction<Hash, Balance, BlockNumber, AccountId>> {
+			Substratekitties::auctions_by_seller(&seller)
+		}
+
+		fn auctions_by_bidder(bidder: AccountId) -> Vec<(Hash, Balance)> {
+			Substratekitties::auctions_by_bidder(&bidder)
+		}
+
+		fn auction_slots() -> Vec<(Hash, BlockNumber, u32)> {
+			Substratekitties::auction_slots()
+		}
+
+		fn auctions_expiring_count(block: BlockNumber) -> u32 {
+			Substratekitties::auctions_expiring_count(block)
+		}
+	}
+
+	impl kitties_api::KittiesApi<Block, AccountId, Hash, Balance, BlockNumber> for Runtime {
+		fn kitty(id: Hash) -> Option<KittyInfo<AccountId, Hash, Balance, BlockNumber>> {
+			Substratekitties::kitty_info(id)
+		}
+
+		fn kitties_owned_by(owner: AccountId, start: u64, count: u64) -> (Vec<Hash>, u64) {
+			Substratekitties::owned_kitties_page(&owner, start, count)
+		}
+
+		fn all_kitties(start: u64, count: u64) -> (Vec<Hash>, u64) {
+			Substratekitties::kitties_page(start, count)
+		}
+
+		fn auction(id: Hash) -> Option<Auction<Hash, Balance, BlockNumber, AccountId>> {
+			Substratekitties::auction_for(id)
+		}
+
+		fn quote_breed(matron: Hash, sire: Hash) -> BreedQuote<Balance, BlockNumber> {
+			Substratekitties::quote_breed(matron, sire)
+		}
+	}
+
+	impl kitties_api::DevSealApi<Block> for Runtime {
+		fn timestamp() -> u64 {
+			Timestamp::now()
+		}
+	}
 }
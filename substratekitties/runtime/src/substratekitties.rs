@@ -1,36 +1,191 @@
 use support::{decl_storage, decl_module, StorageValue, StorageMap,
-    dispatch::Result, ensure, decl_event, traits::{Currency, ReservableCurrency}};
-use system::ensure_signed;
-use runtime_primitives::traits::{As, Hash, Zero};
+    dispatch::Result, ensure, decl_event, traits::{Currency, ReservableCurrency, WithdrawReason}};
+use system::{ensure_signed, ensure_root};
+use runtime_primitives::Permill;
+use runtime_primitives::traits::{As, Hash, Zero, CheckedAdd, CheckedSub, CheckedMul};
 use parity_codec::{Encode, Decode};
 use rstd::cmp;
 use rstd::prelude::*;
+use crate::genetics;
+#[cfg(feature = "std")]
+use runtime_io::{with_storage, StorageOverlay, ChildrenStorageOverlay};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+
+/// (De)serializes balances as decimal strings, JSON numbers can't hold a `u128` exactly.
+#[cfg(feature = "std")]
+pub mod balance_string {
+    use std::fmt::Display;
+    use std::str::FromStr;
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<B: Display, S: Serializer>(balance: &B, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(balance)
+    }
+
+    pub fn deserialize<'de, B: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<B, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(|_| D::Error::custom(format!("Invalid balance `{}`", s)))
+    }
+
+    /// The same for optional balances, `None` is `null`.
+    pub mod option {
+        use std::fmt::Display;
+        use std::str::FromStr;
+        use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+        pub fn serialize<B: Display, S: Serializer>(balance: &Option<B>, serializer: S) -> Result<S::Ok, S::Error> {
+            match balance {
+                Some(balance) => serializer.serialize_some(&balance.to_string()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, B: FromStr, D: Deserializer<'de>>(deserializer: D) -> Result<Option<B>, D::Error> {
+            match Option::<String>::deserialize(deserializer)? {
+                Some(s) => s.parse().map(Some).map_err(|_| D::Error::custom(format!("Invalid balance `{}`", s))),
+                None => Ok(None),
+            }
+        }
+    }
+}
 
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
-#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(
+    serialize = "Hash: Serialize, Balance: std::fmt::Display",
+    deserialize = "Hash: Deserialize<'de>, Balance: std::str::FromStr"
+)))]
 pub struct Kitty<Hash, Balance> {
     id: Hash,
     dna: Hash,
+    #[cfg_attr(feature = "std", serde(with = "balance_string"))]
     price: Balance,
     gen: u64,
 }
 
 #[derive(Encode, Decode, Default, Clone, PartialEq)]
-#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(
+    serialize = "Hash: Serialize, Balance: std::fmt::Display, BlockNumber: Serialize, AccountId: Serialize",
+    deserialize = "Hash: Deserialize<'de>, Balance: std::str::FromStr, BlockNumber: Deserialize<'de>, AccountId: Deserialize<'de>"
+)))]
 pub struct Auction<Hash, Balance, BlockNumber, AccountId> {
     kitty_id: Hash,
     kitty_owner: AccountId,
     expiry: BlockNumber,
+    #[cfg_attr(feature = "std", serde(with = "balance_string"))]
     min_bid: Balance,
+    #[cfg_attr(feature = "std", serde(with = "balance_string"))]
     high_bid: Balance,
     high_bidder: AccountId,
+    kind: AuctionKind<Balance, BlockNumber>,
+    /// Further kitties sold together with `kitty_id`.
+    lot: Vec<Hash>,
+}
+
+#[derive(Encode, Decode, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(
+    serialize = "Balance: std::fmt::Display, BlockNumber: Serialize",
+    deserialize = "Balance: std::str::FromStr, BlockNumber: Deserialize<'de>"
+)))]
+pub enum AuctionKind<Balance, BlockNumber> {
+    /// Ascending auction, the highest bid at `expiry` wins.
+    English,
+    /// Descending auction, the price falls linearly from `start_price` at `start` to
+    /// `end_price` at `expiry` and the first buyer wins.
+    Dutch {
+        #[cfg_attr(feature = "std", serde(with = "balance_string"))]
+        start_price: Balance,
+        #[cfg_attr(feature = "std", serde(with = "balance_string"))]
+        end_price: Balance,
+        start: BlockNumber,
+    },
+    /// Sealed-bid auction. Bids are committed until `commit_end` and revealed until `expiry`.
+    /// The highest revealed bid wins and pays itself, or `second_bid` when `vickrey` is set.
+    Sealed {
+        commit_end: BlockNumber,
+        vickrey: bool,
+        #[cfg_attr(feature = "std", serde(with = "balance_string"))]
+        second_bid: Balance,
+    },
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SealedBid<Hash, Balance> {
+    /// The `AuctionRound` the bid was committed in.
+    round: u64,
+    commitment: Hash,
+    deposit: Balance,
+    revealed: bool,
+}
+
+/// A kitty together with its ownership and breeding state.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(bound(
+    serialize = "AccountId: Serialize, Hash: Serialize, Balance: std::fmt::Display, BlockNumber: Serialize",
+    deserialize = "AccountId: Deserialize<'de>, Hash: Deserialize<'de>, Balance: std::str::FromStr, BlockNumber: Deserialize<'de>"
+)))]
+pub struct KittyInfo<AccountId, Hash, Balance, BlockNumber> {
+    #[cfg_attr(feature = "std", serde(flatten))]
+    pub kitty: Kitty<Hash, Balance>,
+    pub owner: AccountId,
+    pub creator: Option<AccountId>,
+    /// (matron_id, sire_id), `None` for generation 0 kitties.
+    pub parents: Option<(Hash, Hash)>,
+    pub children: u64,
+    pub cooldown_end: BlockNumber,
+    #[cfg_attr(feature = "std", serde(with = "balance_string::option"))]
+    pub siring_fee: Option<Balance>,
+    /// Whether the kitty is locked in an auction, on its own or as part of a lot.
+    pub in_auction: bool,
+    /// The variant every gene slot expresses, in `GeneSlot::ALL` order, for avatars to render.
+    pub traits: Vec<u8>,
+}
+
+/// What breeding two kitties would result in if it happened now.
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BreedQuote<Balance, BlockNumber> {
+    /// Why the kitties can't breed now, `None` when they can.
+    pub error: Option<Vec<u8>>,
+    /// The fee `buy_siring` charges when the sire is offered for siring.
+    pub siring_fee: Option<Balance>,
+    pub child_gen: u64,
+    pub matron_cooldown: BlockNumber,
+    pub sire_cooldown: BlockNumber,
+}
+
+impl<Balance, BlockNumber> Default for AuctionKind<Balance, BlockNumber> {
+    fn default() -> Self {
+        AuctionKind::English
+    }
+}
+
+#[derive(Encode, Decode, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Offer<Balance, BlockNumber> {
+    amount: Balance,
+    expiry: BlockNumber,
 }
 
 pub trait Trait: balances::Trait {
     type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 }
 
-const MAX_AUCTIONS_PER_BLOCK: usize = 2;
+// Expired auctions beyond this budget stay queued and are settled in the following blocks.
+const MAX_SETTLEMENTS_PER_BLOCK: u32 = 8;
+// A settlement that fails is retried this many blocks later, up to `MAX_SETTLEMENT_ATTEMPTS` times.
+const SETTLEMENT_RETRY_DELAY: u64 = 10;
+const MAX_SETTLEMENT_ATTEMPTS: u32 = 3;
+const MAX_OFFERS_PER_BLOCK: usize = 16;
+// The breeding cooldown grows with `gen` up to this many times `BreedingCooldown`.
+const MAX_COOLDOWN_MULTIPLIER: u64 = 16;
+// Upper bound for `InbreedingDepth`, the ancestor sets compared while breeding grow as 2^depth.
+const MAX_INBREEDING_DEPTH: u32 = 4;
 
 decl_event!(
     pub enum Event<T>
@@ -43,10 +198,35 @@ decl_event!(
         Created(AccountId, Hash),
         PriceSet(AccountId, Hash, Balance),
         Transferred(AccountId, AccountId, Hash),
-        Bought(AccountId, AccountId, Hash, Balance),
+        Burned(AccountId, Hash),
+        OfferMade(AccountId, Hash, Balance, BlockNumber),
+        OfferAccepted(AccountId, AccountId, Hash, Balance, Balance, Balance),
+        OfferWithdrawn(AccountId, Hash),
+        OfferExpired(AccountId, Hash),
+        Approval(AccountId, AccountId, Hash),
+        ApprovalForAll(AccountId, AccountId, bool),
+        Bought(AccountId, AccountId, Hash, Balance, Balance, Balance),
+        SiringApproved(AccountId, Hash, AccountId),
+        Bred(AccountId, Hash, Hash, Hash),
+        SiringOffered(AccountId, Hash, Balance),
+        SiringOfferCancelled(AccountId, Hash),
+        SiringPurchased(AccountId, AccountId, Hash, Balance),
         AuctionCreated(Hash, Balance, BlockNumber),
-        Bid(Hash, Balance, AccountId),
-        AuctionFinalized(Hash, Balance, BlockNumber),
+        LotAuctionCreated(Hash, Vec<Hash>, Balance, BlockNumber),
+        DutchAuctionCreated(Hash, Balance, Balance, BlockNumber),
+        SealedAuctionCreated(Hash, Balance, BlockNumber, BlockNumber),
+        RevealPhaseStarted(Hash),
+        BidCommitted(AccountId, Hash, Balance),
+        BidRevealed(AccountId, Hash, Balance),
+        DepositForfeited(AccountId, Hash, Balance),
+        Bid(Hash, Balance, Balance, AccountId),
+        BidWithdrawn(AccountId, Hash, Balance),
+        AuctionFinalized(Hash, Balance, BlockNumber, Balance, Balance),
+        AuctionCancelled(Hash, Balance),
+        AuctionExtended(Hash, BlockNumber, BlockNumber),
+        AuctionReserveNotMet(Hash, Balance),
+        AuctionEndedWithoutBids(Hash),
+        AuctionFailed(Hash, Vec<u8>),
     }
 );
 
@@ -63,15 +243,107 @@ decl_storage! {
         OwnedKittiesCount get(owned_kitty_count): map T::AccountId => u64;
         OwnedKittiesIndex: map T::Hash => u64;
 
+        KittyApprovals get(approved_of): map T::Hash => Option<T::AccountId>;
+        OperatorApprovals get(is_approved_for_all): map (T::AccountId, T::AccountId) => bool;
+
+        MaxBatchSize get(max_batch_size) config(): u32 = 32;
+
+        Offers get(offer_of): map (T::Hash, T::AccountId) => Option<Offer<T::Balance, T::BlockNumber>>;
+        OffersExpireAt get(offers_expire_at): map T::BlockNumber => Vec<(T::Hash, T::AccountId)>;
+        OfferPeriodLimit get(offer_period_limit) config(): T::BlockNumber = T::BlockNumber::sa(17280);
+
+        KittyCreator get(creator_of): map T::Hash => Option<T::AccountId>;
+        MarketplaceFee get(marketplace_fee) config(): Permill;
+        CreatorRoyalty get(creator_royalty) config(): Permill;
+        FeeDestination get(fee_destination) config(): T::AccountId;
+
         KittyAuction get(auction_of): map T::Hash => Option<Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>>;
-        Auctions get(auctions_expire_at): map T::BlockNumber => Vec<(Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>)>;
+        AuctionsExpiring get(auction_expiring_by_index): map (T::BlockNumber, u32) => T::Hash;
+        AuctionsExpiringCount get(auctions_expiring_count): map T::BlockNumber => u32;
+        // The expiry bucket an auction is currently scheduled in and its index there.
+        AuctionSlot get(auction_slot): map T::Hash => Option<(T::BlockNumber, u32)>;
+        // FIFO of blocks whose expired auctions still have to be settled.
+        SettlementQueue: map u64 => T::BlockNumber;
+        SettlementQueueHead: u64;
+        SettlementQueueTail: u64;
+        SettlementAttempts get(settlement_attempts): map T::Hash => u32;
+        // The kitty whose `KittyAuction` entry holds the lot a kitty is auctioned in.
+        LotLeader get(lot_leader_of): map T::Hash => Option<T::Hash>;
+
+        ActiveAuctionsArray get(active_auction_by_index): map u64 => T::Hash;
+        ActiveAuctionsCount get(active_auctions_count): u64;
+        ActiveAuctionsIndex: map T::Hash => u64;
+
+        SellerAuctionsArray get(seller_auction_by_index): map (T::AccountId, u64) => T::Hash;
+        SellerAuctionsCount get(seller_auctions_count): map T::AccountId => u64;
+        SellerAuctionsIndex: map T::Hash => u64;
+
+        BidderAuctionsArray get(bidder_auction_by_index): map (T::AccountId, u64) => T::Hash;
+        BidderAuctionsCount get(bidder_auctions_count): map T::AccountId => u64;
+        BidderAuctionsIndex: map (T::Hash, T::AccountId) => u64;
         AuctionPeriodLimit get(auction_period_limit) config(): T::BlockNumber = T::BlockNumber::sa(17280);
         PredefinedAuctionPeriodLimit get(predefined_auction_period_limit) config(): T::BlockNumber = T::BlockNumber::sa(20);
-        Bids get(bid_of): map (T::Hash, T::AccountId) => T::Balance;
-        BidAccounts get(bid_accounts): map T::Hash => Vec<T::AccountId>;
+        AuctionExtensionWindow get(auction_extension_window) config(): T::BlockNumber = T::BlockNumber::sa(5);
+        AuctionExtensionPeriod get(auction_extension_period) config(): T::BlockNumber = T::BlockNumber::sa(5);
+        MinBidIncrement get(min_bid_increment) config(): T::Balance;
+        MinBidIncrementPermill get(min_bid_increment_permill) config(): Permill;
+        // Counts the auctions a kitty was put in, bids only count in the round they were made in.
+        AuctionRound get(auction_round): map T::Hash => u64;
+        // (round, amount) reserved by each bidder, outbid accounts take theirs back with `withdraw_bid`.
+        Bids get(bid_of): map (T::Hash, T::AccountId) => Option<(u64, T::Balance)>;
+        SealedBids get(sealed_bid_of): map (T::Hash, T::AccountId) => Option<SealedBid<T::Hash, T::Balance>>;
+        UnrevealedForfeit get(unrevealed_forfeit) config(): Permill = Permill::from_percent(50);
+        // Kept out of `Auction` so bidders only learn it when the auction fails to meet it.
+        ReservePrices: map T::Hash => Option<T::Balance>;
+        AuctionCancelPenalty get(auction_cancel_penalty) config(): Permill = Permill::from_percent(5);
+
+        MutationRate get(mutation_rate) config(): Permill = Permill::from_percent(1);
+        BreedingCooldown get(breeding_cooldown) config(): T::BlockNumber = T::BlockNumber::sa(10);
+        CooldownEnd get(cooldown_end): map T::Hash => T::BlockNumber;
+        SireAllowedTo get(sire_allowed_to): map T::Hash => Option<T::AccountId>;
+        // (matron_id, sire_id) of every bred kitty
+        KittyParents get(parents_of): map T::Hash => Option<(T::Hash, T::Hash)>;
+        KittyChildrenArray get(child_of_kitty_by_index): map (T::Hash, u64) => T::Hash;
+        KittyChildrenCount get(children_count): map T::Hash => u64;
+        InbreedingDepth get(inbreeding_depth) config(): u32 = 1;
+        SiringOffers get(siring_offer_of): map T::Hash => Option<T::Balance>;
 
         Nonce: u64;
     }
+
+    add_extra_genesis {
+        // (owner, id, dna, price, gen)
+        config(kitties): Vec<(T::AccountId, T::Hash, T::Hash, T::Balance, u64)>;
+        // (kitty_id, min_bid, expiry), opened on behalf of the kitty owner
+        config(auctions): Vec<(T::Hash, T::Balance, T::BlockNumber)>;
+
+        build(|storage: &mut StorageOverlay, _: &mut ChildrenStorageOverlay, config: &GenesisConfig<T>| {
+            // The same limits the root calls enforce, a bad chain spec is rejected before it starts
+            assert!(config.inbreeding_depth <= MAX_INBREEDING_DEPTH, "genesis inbreeding depth is above the maximum");
+            assert!(<Module<T>>::parts(config.marketplace_fee) + <Module<T>>::parts(config.creator_royalty)
+                <= <Module<T>>::parts(Permill::one()), "genesis marketplace fee and creator royalty exceed the price");
+
+            with_storage(storage, || {
+                for &(ref owner, id, dna, price, gen) in &config.kitties {
+                    let kitty = Kitty { id, dna, price, gen };
+
+                    <Module<T>>::mint(owner.clone(), id, kitty)
+                        .expect("genesis kitties must have unique ids");
+                }
+
+                for &(kitty_id, min_bid, expiry) in &config.auctions {
+                    let owner = <Module<T>>::owner_of(kitty_id)
+                        .expect("genesis auctions must refer to a genesis kitty");
+                    let now = <system::Module<T>>::block_number();
+                    assert!(expiry > now, "genesis auctions must expire after the genesis block");
+                    assert!(expiry <= now + config.auction_period_limit, "genesis auctions must expire within the auction period limit");
+
+                    <Module<T>>::open_auction(owner, kitty_id, min_bid, expiry, AuctionKind::English, vec![])
+                        .expect("opening an auction cannot fail");
+                }
+            });
+        });
+    }
 }
 
 decl_module! {
@@ -99,6 +371,39 @@ decl_module! {
             Ok(())
         }
 
+        fn create_kitties(origin, count: u32) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_batch_size(count as usize)?;
+
+            Self::owned_kitty_count(&sender).checked_add(u64::from(count))
+                .ok_or("Overflow adding new kitties to account balance")?;
+            Self::all_kitties_count().checked_add(u64::from(count))
+                .ok_or("Overflow adding new kitties to total supply")?;
+
+            let nonce = <Nonce<T>>::get();
+            let kitty_ids: Vec<T::Hash> = (0..u64::from(count))
+                .map(|i| (<system::Module<T>>::random_seed(), &sender, nonce + i)
+                    .using_encoded(<T as system::Trait>::Hashing::hash))
+                .collect();
+            ensure!(kitty_ids.iter().all(|id| !<KittyOwner<T>>::exists(id)), "Kitty already exists");
+
+            for kitty_id in kitty_ids {
+                let new_kitty = Kitty {
+                    id: kitty_id,
+                    dna: kitty_id,
+                    price: <T::Balance as As<u64>>::sa(0),
+                    gen: 0,
+                };
+
+                Self::mint(sender.clone(), kitty_id, new_kitty)?;
+            }
+
+            <Nonce<T>>::mutate(|n| *n += u64::from(count));
+
+            Ok(())
+        }
+
         fn set_price(origin, kitty_id: T::Hash, new_price: T::Balance) -> Result {
             let sender = ensure_signed(origin)?;
 
@@ -107,6 +412,8 @@ decl_module! {
             let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
             ensure!(owner == sender, "You do not own this cat");
 
+            Self::ensure_not_auctioned(kitty_id)?;
+
             let mut kitty = Self::kitty(kitty_id);
             kitty.price = new_price;
 
@@ -123,7 +430,107 @@ decl_module! {
             let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
             ensure!(owner == sender, "You do not own this kitty");
 
-            Self::transfer_from(sender, to, kitty_id)?;
+            Self::do_transfer(sender, to, kitty_id)?;
+
+            Ok(())
+        }
+
+        fn batch_transfer(origin, transfers: Vec<(T::AccountId, T::Hash)>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_batch_size(transfers.len())?;
+
+            for (i, (to, kitty_id)) in transfers.iter().enumerate() {
+                ensure!(!transfers[..i].iter().any(|(_, id)| id == kitty_id), "The batch contains the same kitty twice");
+                Self::ensure_can_transfer(&sender, to, *kitty_id)?;
+            }
+
+            for (to, kitty_id) in transfers {
+                Self::do_transfer(sender.clone(), to, kitty_id)?;
+            }
+
+            Ok(())
+        }
+
+        fn batch_set_price(origin, prices: Vec<(T::Hash, T::Balance)>) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_batch_size(prices.len())?;
+
+            for (kitty_id, _) in &prices {
+                let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+                ensure!(owner == sender, "You do not own this cat");
+                Self::ensure_not_auctioned(*kitty_id)?;
+            }
+
+            for (kitty_id, new_price) in prices {
+                <Kitties<T>>::mutate(kitty_id, |kitty| kitty.price = new_price);
+
+                Self::deposit_event(RawEvent::PriceSet(sender.clone(), kitty_id, new_price));
+            }
+
+            Ok(())
+        }
+
+        fn set_max_batch_size(origin, max_batch_size: u32) -> Result {
+            ensure_root(origin)?;
+
+            <MaxBatchSize<T>>::put(max_batch_size);
+
+            Ok(())
+        }
+
+        fn approve(origin, to: T::AccountId, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender || Self::is_approved_for_all((owner.clone(), sender)),
+                "You are neither the owner nor an operator of the owner");
+            ensure!(to != owner, "The owner cannot be approved for its own kitty");
+
+            <KittyApprovals<T>>::insert(kitty_id, &to);
+
+            Self::deposit_event(RawEvent::Approval(owner, to, kitty_id));
+
+            Ok(())
+        }
+
+        fn set_approval_for_all(origin, operator: T::AccountId, approved: bool) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(operator != sender, "You cannot be your own operator");
+
+            if approved {
+                <OperatorApprovals<T>>::insert((sender.clone(), operator.clone()), true);
+            } else {
+                <OperatorApprovals<T>>::remove((sender.clone(), operator.clone()));
+            }
+
+            Self::deposit_event(RawEvent::ApprovalForAll(sender, operator, approved));
+
+            Ok(())
+        }
+
+        fn transfer_from(origin, from: T::AccountId, to: T::AccountId, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == from, "'from' account does not own this kitty");
+            ensure!(Self::is_approved_or_owner(&sender, &owner, kitty_id),
+                "You are not approved to transfer this kitty");
+
+            Self::do_transfer(from, to, kitty_id)?;
+
+            Ok(())
+        }
+
+        fn burn_kitty(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
+
+            Self::burn(sender, kitty_id)?;
 
             Ok(())
         }
@@ -142,9 +549,11 @@ decl_module! {
             ensure!(!kitty_price.is_zero(), "The cat you want to buy is not for sale");
             ensure!(kitty_price <= max_price, "The cat you want to buy costs more than your max price");
 
-            <balances::Module<T> as Currency<_>>::transfer(&sender, &owner, kitty_price)?;
+            Self::ensure_can_transfer(&owner, &sender, kitty_id)?;
+
+            let (fee, royalty) = Self::pay_sale(&sender, &owner, kitty_id, kitty_price)?;
 
-            Self::transfer_from(owner.clone(), sender.clone(), kitty_id)
+            Self::do_transfer(owner.clone(), sender.clone(), kitty_id)
                 .expect("`owner` is shown to own the kitty; \
                 `owner` must have greater than 0 kitties, so transfer cannot cause underflow; \
                 `all_kitty_count` shares the same type as `owned_kitty_count` \
@@ -155,262 +564,2303 @@ decl_module! {
             kitty.price = <T::Balance as As<u64>>::sa(0);
             <Kitties<T>>::insert(kitty_id, kitty);
 
-            Self::deposit_event(RawEvent::Bought(sender, owner, kitty_id, kitty_price));
+            Self::deposit_event(RawEvent::Bought(sender, owner, kitty_id, kitty_price, fee, royalty));
 
             Ok(())
         }
 
-        fn breed_kitty(origin, kitty_id_1: T::Hash, kitty_id_2: T::Hash) -> Result{
+        fn make_offer(origin, kitty_id: T::Hash, amount: T::Balance, expiry: T::BlockNumber) -> Result {
             let sender = ensure_signed(origin)?;
 
-            ensure!(<Kitties<T>>::exists(kitty_id_1), "This cat 1 does not exist");
-            ensure!(<Kitties<T>>::exists(kitty_id_2), "This cat 2 does not exist");
+            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
 
-            let nonce = <Nonce<T>>::get();
-            let random_hash = (<system::Module<T>>::random_seed(), &sender, nonce)
-                .using_encoded(<T as system::Trait>::Hashing::hash);
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner != sender, "You can't make an offer for your own cat");
 
-            let kitty_1 = Self::kitty(kitty_id_1);
-            let kitty_2 = Self::kitty(kitty_id_2);
+            ensure!(!amount.is_zero(), "The offer has to be greater than zero");
+            ensure!(!<Offers<T>>::exists((kitty_id, sender.clone())), "You already have an offer for this cat, withdraw it first");
 
-            let mut final_dna = kitty_1.dna;
+            ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
+            ensure!(expiry <= <system::Module<T>>::block_number() + Self::offer_period_limit(), "The expiry has be lower than the limit block number");
 
-            for (i, (dna_2_element, r)) in kitty_2.dna.as_ref().iter().zip(random_hash.as_ref().iter()).enumerate() {
-                if r % 2 == 0 {
-                    final_dna.as_mut()[i] = *dna_2_element;
-                }
-            }
+            let offers = Self::offers_expire_at(expiry);
+            ensure!(offers.len() < MAX_OFFERS_PER_BLOCK, "Maximum number of offers is reached for the target block, try another block");
 
-            let new_kitty = Kitty {
-                id: random_hash,
-                dna: final_dna,
-                price: <T::Balance as As<u64>>::sa(0),
-                gen: cmp::max(kitty_1.gen, kitty_2.gen) + 1,
-            };
+            <balances::Module<T>>::reserve(&sender, amount)?;
 
-            Self::mint(sender, random_hash, new_kitty)?;
+            <Offers<T>>::insert((kitty_id, sender.clone()), Offer { amount, expiry });
+            <OffersExpireAt<T>>::mutate(expiry, |offers| offers.push((kitty_id, sender.clone())));
 
-            <Nonce<T>>::mutate(|n| *n += 1);
+            Self::deposit_event(RawEvent::OfferMade(sender, kitty_id, amount, expiry));
 
             Ok(())
         }
 
-        fn create_auction(origin, kitty_id: T::Hash, min_bid: T::Balance, expiry: T::BlockNumber) -> Result {
+        fn accept_offer(origin, kitty_id: T::Hash, buyer: T::AccountId) -> Result {
             let sender = ensure_signed(origin)?;
 
-            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
-
             let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
-            ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+            ensure!(owner == sender, "You do not own this cat");
+            // An offer made before its maker came to own the cat stays behind, it can only be withdrawn
+            ensure!(buyer != owner, "You can't accept your own offer");
 
-            ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
-            ensure!(expiry <= <system::Module<T>>::block_number() + Self::auction_period_limit(), "The expiry has be lower than the limit block number");
+            let offer = Self::offer_of((kitty_id, buyer.clone())).ok_or("There is no offer from this account")?;
+            ensure!(<system::Module<T>>::block_number() < offer.expiry, "This offer is expired.");
 
-            let auctions = Self::auctions_expire_at(expiry);
-            ensure!(auctions.len() < MAX_AUCTIONS_PER_BLOCK, "Maximum number of auctions is reached for the target block, try another block");
+            Self::ensure_can_transfer(&owner, &buyer, kitty_id)?;
 
-            let new_auction = Auction {
-                kitty_id,
-                kitty_owner: owner,
-                expiry,
-                min_bid,
-                high_bid: min_bid,
-                high_bidder: sender,
-            };
+            // The offer is paid out of its reserve, check the whole payment before releasing it
+            let (legs, fee, royalty) = Self::sale_legs(&buyer, &owner, &[kitty_id], offer.amount)?;
+            Self::ensure_can_pay(&buyer, &legs, offer.amount)?;
 
-            <KittyAuction<T>>::insert(kitty_id, &new_auction);
-            <Auctions<T>>::mutate(expiry, |auctions| auctions.push(new_auction.clone()));
+            let _ = <balances::Module<T>>::unreserve(&buyer, offer.amount);
+            Self::pay_legs(&buyer, legs)?;
 
-            Self::deposit_event(RawEvent::AuctionCreated(kitty_id, min_bid, expiry));
+            Self::remove_offer(kitty_id, buyer.clone(), offer.expiry);
 
-            Ok (())
+            Self::do_transfer(owner.clone(), buyer.clone(), kitty_id)
+                .expect("`owner` is shown to own the kitty and the kitty has no open auction; \
+                `owner` must have greater than 0 kitties, so transfer cannot cause underflow; \
+                `all_kitty_count` shares the same type as `owned_kitty_count` \
+                and minting ensure there won't ever be more than `max()` kitties, \
+                which means transfer cannot cause an overflow; \
+                qed");
+
+            <Kitties<T>>::mutate(kitty_id, |kitty| kitty.price = <T::Balance as As<u64>>::sa(0));
+
+            Self::deposit_event(RawEvent::OfferAccepted(owner, buyer, kitty_id, offer.amount, fee, royalty));
+
+            Ok(())
         }
 
-        fn predefined_create_auction(origin, kitty_id: T::Hash, min_bid: T::Balance) -> Result {
+        fn withdraw_offer(origin, kitty_id: T::Hash) -> Result {
             let sender = ensure_signed(origin)?;
 
-            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+            let offer = Self::offer_of((kitty_id, sender.clone())).ok_or("You have no offer for this cat")?;
 
-            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
-            ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+            let _ = <balances::Module<T>>::unreserve(&sender, offer.amount);
+            Self::remove_offer(kitty_id, sender.clone(), offer.expiry);
 
-            // ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
-            // ensure!(expiry <= <system::Module<T>>::block_number() + Self::auction_period_limit(), "The expiry has be lower than the limit block number");
+            Self::deposit_event(RawEvent::OfferWithdrawn(sender, kitty_id));
 
-            let expiry = <system::Module<T>>::block_number() + Self::predefined_auction_period_limit();
+            Ok(())
+        }
 
-            let auctions = Self::auctions_expire_at(expiry);
-            ensure!(auctions.len() < MAX_AUCTIONS_PER_BLOCK, "Maximum number of auctions is reached for the target block, try another block");
+        fn breed_kitty(origin, matron_id: T::Hash, sire_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
 
-            let new_auction = Auction {
-                kitty_id,
-                kitty_owner: owner,
-                expiry,
-                min_bid,
-                high_bid: min_bid,
-                high_bidder: sender,
-            };
+            let matron_owner = Self::owner_of(matron_id).ok_or("No owner for the matron")?;
+            ensure!(matron_owner == sender, "You do not own the matron");
 
-            <KittyAuction<T>>::insert(kitty_id, &new_auction);
-            <Auctions<T>>::mutate(expiry, |auctions| auctions.push(new_auction.clone()));
+            let sire_owner = Self::owner_of(sire_id).ok_or("No owner for the sire")?;
+            let sire_approved = Self::sire_allowed_to(sire_id) == Some(sender.clone());
+            ensure!(sire_owner == sender || sire_approved, "You do not own the sire and its owner has not approved siring");
 
-            Self::deposit_event(RawEvent::AuctionCreated(kitty_id, min_bid, expiry));
+            Self::breed(sender, matron_id, sire_id)?;
 
-            Ok (())
+            if sire_approved {
+                <SireAllowedTo<T>>::remove(sire_id);
+            }
+
+            Ok(())
         }
 
-        fn bid_auction(origin, kitty_id: T::Hash, bid: T::Balance) -> Result {
+        fn approve_siring(origin, sire_id: T::Hash, to: T::AccountId) -> Result {
             let sender = ensure_signed(origin)?;
 
-            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+            let owner = Self::owner_of(sire_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
-            ensure!(owner != sender, "You can't bid for your own cat");
+            <SireAllowedTo<T>>::insert(sire_id, &to);
 
-            let mut auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
-            ensure!(<system::Module<T>>::block_number() < auction.expiry, "This auction is expired.");
+            Self::deposit_event(RawEvent::SiringApproved(sender, sire_id, to));
 
-            ensure!(bid > auction.high_bid, "Your bid has to be greater than the highest bid.");
+            Ok(())
+        }
 
-            ensure!(<balances::Module<T>>::free_balance(sender.clone()) >= bid, "You don't have enough free balance for this bid");
+        fn offer_siring(origin, kitty_id: T::Hash, fee: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
 
-            auction.high_bid = bid;
-            auction.high_bidder = sender.clone();
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-            <KittyAuction<T>>::insert(kitty_id, &auction);
-            <Auctions<T>>::mutate(auction.expiry, |auctions| {
-                for stored_auction in auctions {
-                    if stored_auction.kitty_id == kitty_id {
-                        *stored_auction = auction.clone();
-                    }
-                }
-            });
+            Self::ensure_not_auctioned(kitty_id)?;
 
-            if <Bids<T>>::exists((kitty_id, sender.clone())) {
-                let escrow_balance = Self::bid_of((kitty_id, sender.clone()));
-                <balances::Module<T>>::reserve(&sender, bid - escrow_balance)?;
-            } else {
-                <balances::Module<T>>::reserve(&sender, bid)?;
-            }
-            <Bids<T>>::insert((kitty_id, sender.clone()), bid);
-            <BidAccounts<T>>::mutate(kitty_id, |accounts| accounts.push(sender.clone()));
+            <SiringOffers<T>>::insert(kitty_id, fee);
 
-            Self::deposit_event(RawEvent::Bid(kitty_id, auction.high_bid, auction.high_bidder));
+            Self::deposit_event(RawEvent::SiringOffered(sender, kitty_id, fee));
 
-            Ok (())
+            Ok(())
         }
 
-        fn on_finalize() {
-            let auctions = Self::auctions_expire_at(<system::Module<T>>::block_number());
-
-            for auction in &auctions {
-                let owned_kitty_count_from = Self::owned_kitty_count(&auction.kitty_owner);
-                let owned_kitty_count_to = Self::owned_kitty_count(&auction.high_bidder);
-                if owned_kitty_count_to.checked_add(1).is_some() &&
-                   owned_kitty_count_from.checked_sub(1).is_some() &&
-                   auction.kitty_owner != auction.high_bidder
-                {
-                    <KittyAuction<T>>::remove(auction.kitty_id);
-
-                    let _ = <balances::Module<T>>::unreserve(&auction.high_bidder, auction.high_bid);
-
-                    let _currency_transfer = <balances::Module<T> as Currency<_>>::transfer(&auction.high_bidder, &auction.kitty_owner, auction.high_bid);
-                    match _currency_transfer {
-                        Err(_e) => continue,
-                        Ok(_v) => {
-                            let _kitty_transfer = Self::transfer_from(auction.kitty_owner.clone(), auction.high_bidder.clone(), auction.kitty_id);
-                            match _kitty_transfer {
-                                Err(_e) => continue,
-                                Ok(_v) => {
-                                    Self::deposit_event(RawEvent::AuctionFinalized(auction.kitty_id, auction.high_bid, auction.expiry));
-                                },
-                            }
-                        },
-                    }
-                }
-            }
+        fn cancel_siring_offer(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
 
-            for auction in &auctions {
-                <Auctions<T>>::remove(<system::Module<T>>::block_number());
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You do not own this kitty");
 
-                let bid_accounts = Self::bid_accounts(auction.kitty_id);
+            ensure!(<SiringOffers<T>>::exists(kitty_id), "This kitty is not offered for siring");
+            <SiringOffers<T>>::remove(kitty_id);
 
-                for account in bid_accounts {
-                    let bid_balance = Self::bid_of((auction.kitty_id, account.clone()));
-                    let _ = <balances::Module<T>>::unreserve(&account, bid_balance);
-                    <Bids<T>>::remove((auction.kitty_id, account));
-                }
+            Self::deposit_event(RawEvent::SiringOfferCancelled(sender, kitty_id));
 
-                <BidAccounts<T>>::remove(auction.kitty_id);
-            }
+            Ok(())
         }
-    }
-}
 
-impl<T: Trait> Module<T> {
-    fn mint(to: T::AccountId, kitty_id: T::Hash, new_kitty: Kitty<T::Hash, T::Balance>) -> Result {
-        ensure!(!<KittyOwner<T>>::exists(kitty_id), "Kitty already exists");
+        fn buy_siring(origin, sire_id: T::Hash, matron_id: T::Hash, max_fee: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
 
-        let owned_kitty_count = Self::owned_kitty_count(&to);
+            let matron_owner = Self::owner_of(matron_id).ok_or("No owner for the matron")?;
+            ensure!(matron_owner == sender, "You do not own the matron");
 
-        let new_owned_kitty_count = owned_kitty_count.checked_add(1)
-            .ok_or("Overflow adding a new kitty to account balance")?;
+            let sire_owner = Self::owner_of(sire_id).ok_or("No owner for the sire")?;
+            ensure!(sire_owner != sender, "You can't buy siring from your own kitty");
 
-        let all_kitties_count = Self::all_kitties_count();
+            let fee = Self::siring_offer_of(sire_id).ok_or("This kitty is not offered for siring")?;
+            ensure!(fee <= max_fee, "The siring fee is more than your max fee");
 
-        let new_all_kitties_count = all_kitties_count.checked_add(1)
-            .ok_or("Overflow adding a new kitty to total supply")?;
+            Self::ensure_not_auctioned(sire_id)?;
 
-        <Kitties<T>>::insert(kitty_id, new_kitty);
-        <KittyOwner<T>>::insert(kitty_id, &to);
+            Self::ensure_can_breed(matron_id, sire_id)?;
 
-        <AllKittiesArray<T>>::insert(all_kitties_count, kitty_id);
-        <AllKittiesCount<T>>::put(new_all_kitties_count);
-        <AllKittiesIndex<T>>::insert(kitty_id, all_kitties_count);
+            <balances::Module<T> as Currency<_>>::transfer(&sender, &sire_owner, fee)?;
 
-        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count), kitty_id);
-        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count);
-        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count);
+            Self::breed(sender.clone(), matron_id, sire_id)?;
 
-        Self::deposit_event(RawEvent::Created(to, kitty_id));
+            Self::deposit_event(RawEvent::SiringPurchased(sender, sire_owner, sire_id, fee));
 
-        Ok(())
-    }
+            Ok(())
+        }
 
-    fn transfer_from(from: T::AccountId, to: T::AccountId, kitty_id: T::Hash) -> Result {
-        let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+        fn set_mutation_rate(origin, rate: Permill) -> Result {
+            ensure_root(origin)?;
 
-        ensure!(owner == from, "'from' account does not own this kitty");
+            <MutationRate<T>>::put(rate);
 
-        ensure!(!<KittyAuction<T>>::exists(kitty_id), "This kitty has an open auction.");
+            Ok(())
+        }
 
-        let owned_kitty_count_from = Self::owned_kitty_count(&from);
-        let owned_kitty_count_to = Self::owned_kitty_count(&to);
+        fn set_inbreeding_depth(origin, depth: u32) -> Result {
+            ensure_root(origin)?;
 
-        let new_owned_kitty_count_to = owned_kitty_count_to.checked_add(1)
-            .ok_or("Transfer causes overflow of 'to' kitty balance")?;
+            ensure!(depth <= MAX_INBREEDING_DEPTH, "The inbreeding depth is above the maximum");
+            <InbreedingDepth<T>>::put(depth);
 
-        let new_owned_kitty_count_from = owned_kitty_count_from.checked_sub(1)
-            .ok_or("Transfer causes underflow of 'from' kitty balance")?;
+            Ok(())
+        }
 
-        let kitty_index = <OwnedKittiesIndex<T>>::get(kitty_id);
-        if kitty_index != new_owned_kitty_count_from {
-            let last_kitty_id = <OwnedKittiesArray<T>>::get((from.clone(), new_owned_kitty_count_from));
-            <OwnedKittiesArray<T>>::insert((from.clone(), kitty_index), last_kitty_id);
-            <OwnedKittiesIndex<T>>::insert(last_kitty_id, kitty_index);
+        fn set_marketplace_fee(origin, fee: Permill) -> Result {
+            ensure_root(origin)?;
+
+            ensure!(Self::parts(fee) + Self::parts(Self::creator_royalty()) <= Self::parts(Permill::one()),
+                "The marketplace fee and creator royalty exceed the price");
+            <MarketplaceFee<T>>::put(fee);
+
+            Ok(())
         }
 
-        <KittyOwner<T>>::insert(&kitty_id, &to);
-        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count_to);
+        fn set_creator_royalty(origin, royalty: Permill) -> Result {
+            ensure_root(origin)?;
 
-        <OwnedKittiesArray<T>>::remove((from.clone(), new_owned_kitty_count_from));
-        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count_to), kitty_id);
+            ensure!(Self::parts(Self::marketplace_fee()) + Self::parts(royalty) <= Self::parts(Permill::one()),
+                "The marketplace fee and creator royalty exceed the price");
+            <CreatorRoyalty<T>>::put(royalty);
 
-        <OwnedKittiesCount<T>>::insert(&from, new_owned_kitty_count_from);
-        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count_to);
+            Ok(())
+        }
 
-        Self::deposit_event(RawEvent::Transferred(from, to, kitty_id));
+        fn set_fee_destination(origin, destination: T::AccountId) -> Result {
+            ensure_root(origin)?;
 
-        Ok(())
+            <FeeDestination<T>>::put(destination);
+
+            Ok(())
+        }
+
+        fn set_bid_increment(origin, absolute: T::Balance, relative: Permill) -> Result {
+            ensure_root(origin)?;
+
+            <MinBidIncrement<T>>::put(absolute);
+            <MinBidIncrementPermill<T>>::put(relative);
+
+            Ok(())
+        }
+
+        fn create_auction(origin, kitty_id: T::Hash, min_bid: T::Balance, expiry: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+
+            ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
+            ensure!(expiry <= <system::Module<T>>::block_number() + Self::auction_period_limit(), "The expiry has be lower than the limit block number");
+
+            Self::open_auction(owner, kitty_id, min_bid, expiry, AuctionKind::English, vec![])
+        }
+
+        /// Auctions several kitties as one lot. The first kitty leads the lot and bids go through
+        /// it, the royalty is paid to the creator of every kitty on its share of the price.
+        fn create_lot_auction(origin, kitty_ids: Vec<T::Hash>, min_bid: T::Balance, expiry: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            Self::ensure_batch_size(kitty_ids.len())?;
+            ensure!(kitty_ids.len() > 1, "A lot needs at least two kitties");
+            for (i, kitty_id) in kitty_ids.iter().enumerate() {
+                ensure!(!kitty_ids[..i].contains(kitty_id), "The batch contains the same kitty twice");
+                let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+                ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+            }
+
+            ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
+            ensure!(expiry <= <system::Module<T>>::block_number() + Self::auction_period_limit(), "The expiry has be lower than the limit block number");
+
+            let lead = kitty_ids[0];
+            let lot = kitty_ids[1..].to_vec();
+            Self::open_auction(sender, lead, min_bid, expiry, AuctionKind::English, lot)
+        }
+
+        fn create_dutch_auction(origin, kitty_id: T::Hash, start_price: T::Balance, end_price: T::Balance, duration: T::BlockNumber) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+
+            ensure!(start_price >= end_price, "The start price has to be at least the end price");
+            ensure!(!duration.is_zero(), "The duration has to be greater than zero");
+            ensure!(duration <= Self::auction_period_limit(), "The duration has be lower than the auction period limit");
+
+            let start = <system::Module<T>>::block_number();
+            let expiry = start + duration;
+
+            Self::open_auction(owner, kitty_id, end_price, expiry, AuctionKind::Dutch { start_price, end_price, start }, vec![])
+        }
+
+        fn create_sealed_auction(origin, kitty_id: T::Hash, min_bid: T::Balance, commit_period: T::BlockNumber,
+            reveal_period: T::BlockNumber, vickrey: bool) -> Result
+        {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+
+            ensure!(!commit_period.is_zero() && !reveal_period.is_zero(), "The commit and reveal periods have to be greater than zero");
+            ensure!(commit_period + reveal_period <= Self::auction_period_limit(), "The duration has be lower than the auction period limit");
+
+            let commit_end = <system::Module<T>>::block_number() + commit_period;
+            let expiry = commit_end + reveal_period;
+
+            Self::open_auction(owner, kitty_id, min_bid, expiry, AuctionKind::Sealed { commit_end, vickrey, second_bid: min_bid }, vec![])
+        }
+
+        fn predefined_create_auction(origin, kitty_id: T::Hash, min_bid: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner == sender, "You can't set an auction for a cat you don't own");
+
+            // ensure!(expiry > <system::Module<T>>::block_number(), "The expiry has to be greater than the current block number");
+            // ensure!(expiry <= <system::Module<T>>::block_number() + Self::auction_period_limit(), "The expiry has be lower than the limit block number");
+
+            let expiry = <system::Module<T>>::block_number() + Self::predefined_auction_period_limit();
+
+            Self::open_auction(owner, kitty_id, min_bid, expiry, AuctionKind::English, vec![])
+        }
+
+        fn bid_auction(origin, kitty_id: T::Hash, bid: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            ensure!(<Kitties<T>>::exists(kitty_id), "This cat does not exist");
+
+            let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+            ensure!(owner != sender, "You can't bid for your own cat");
+
+            let mut auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
+            ensure!(auction.kind == AuctionKind::English, "This auction does not take bids");
+            ensure!(<system::Module<T>>::block_number() < auction.expiry, "This auction is expired.");
+
+            ensure!(bid > auction.high_bid, "Your bid has to be greater than the highest bid.");
+            ensure!(bid >= Self::min_next_bid(&auction), "Your bid has to exceed the highest bid by at least the minimum increment.");
+
+            // A bid that was not withdrawn yet is topped up, one left from an earlier auction of
+            // this cat is released instead
+            let round = Self::auction_round(kitty_id);
+            let (escrow_balance, stale_balance) = match Self::bid_of((kitty_id, sender.clone())) {
+                Some((bid_round, amount)) if bid_round == round => (amount, Zero::zero()),
+                Some((_, amount)) => (Zero::zero(), amount),
+                None => (Zero::zero(), Zero::zero()),
+            };
+            let top_up = bid.checked_sub(&escrow_balance).ok_or("Your bid has to be greater than your previous bid")?;
+            ensure!(<balances::Module<T>>::free_balance(&sender) >= top_up, "You don't have enough free balance for this bid");
+            <balances::Module<T>>::reserve(&sender, top_up)?;
+            let _ = <balances::Module<T>>::unreserve(&sender, stale_balance);
+            <Bids<T>>::insert((kitty_id, sender.clone()), (round, bid));
+            Self::index_bid(kitty_id, &sender);
+
+            let previous_bid = auction.high_bid;
+            auction.high_bid = bid;
+            auction.high_bidder = sender.clone();
+
+            // Bids close to the expiry push it out, so there is always time to answer them
+            let now = <system::Module<T>>::block_number();
+            if now + Self::auction_extension_window() >= auction.expiry {
+                let old_expiry = auction.expiry;
+                auction.expiry = old_expiry + Self::auction_extension_period();
+                Self::unschedule_auction(kitty_id);
+                Self::schedule_auction(kitty_id, auction.expiry);
+
+                Self::deposit_event(RawEvent::AuctionExtended(kitty_id, old_expiry, auction.expiry));
+            }
+            <KittyAuction<T>>::insert(kitty_id, &auction);
+
+            Self::deposit_event(RawEvent::Bid(kitty_id, previous_bid, auction.high_bid, auction.high_bidder));
+
+            Ok (())
+        }
+
+        fn withdraw_bid(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let (_, bid_balance) = Self::bid_of((kitty_id, sender.clone())).ok_or("You have no bid for this cat")?;
+            if let Some(auction) = Self::auction_of(kitty_id) {
+                ensure!(auction.high_bidder != sender, "You can't withdraw the highest bid");
+            }
+
+            <Bids<T>>::remove((kitty_id, sender.clone()));
+            let _ = <balances::Module<T>>::unreserve(&sender, bid_balance);
+            Self::unindex_bid(kitty_id, &sender);
+
+            Self::deposit_event(RawEvent::BidWithdrawn(sender, kitty_id, bid_balance));
+
+            Ok(())
+        }
+
+        /// Commits to a sealed bid with `commitment = sealed_commitment(bid, salt)`. The deposit
+        /// is reserved and has to cover the bid, so it should not give the bid away.
+        fn commit_bid(origin, kitty_id: T::Hash, commitment: T::Hash, deposit: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
+            ensure!(auction.kitty_owner != sender, "You can't bid for your own cat");
+            let commit_end = match auction.kind {
+                AuctionKind::Sealed { commit_end, .. } => commit_end,
+                _ => return Err("This is not a sealed auction"),
+            };
+            ensure!(<system::Module<T>>::block_number() < commit_end, "The commit phase of this auction is over");
+            let round = Self::auction_round(kitty_id);
+            let stale_bid = Self::sealed_bid_of((kitty_id, sender.clone()));
+            if let Some(ref stale_bid) = stale_bid {
+                ensure!(stale_bid.round != round, "You already committed a bid for this cat");
+            }
+
+            <balances::Module<T>>::reserve(&sender, deposit)?;
+            // A bid left from an earlier auction of this cat is withdrawn as if its owner did it
+            if let Some(stale_bid) = stale_bid {
+                Self::release_sealed_bid(kitty_id, sender.clone(), stale_bid);
+            }
+            <SealedBids<T>>::insert((kitty_id, sender.clone()), SealedBid { round, commitment, deposit, revealed: false });
+            Self::index_bid(kitty_id, &sender);
+
+            Self::deposit_event(RawEvent::BidCommitted(sender, kitty_id, deposit));
+
+            Ok(())
+        }
+
+        fn reveal_bid(origin, kitty_id: T::Hash, bid: T::Balance, salt: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let mut auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
+            let now = <system::Module<T>>::block_number();
+            let (commit_end, vickrey, second_bid) = match auction.kind {
+                AuctionKind::Sealed { commit_end, vickrey, second_bid } => (commit_end, vickrey, second_bid),
+                _ => return Err("This is not a sealed auction"),
+            };
+            ensure!(now >= commit_end, "The reveal phase of this auction has not started");
+            ensure!(now < auction.expiry, "This auction is expired.");
+
+            let mut sealed_bid = Self::sealed_bid_of((kitty_id, sender.clone()))
+                .filter(|sealed_bid| sealed_bid.round == Self::auction_round(kitty_id))
+                .ok_or("You have no bid for this cat")?;
+            ensure!(!sealed_bid.revealed, "You already revealed your bid");
+            ensure!(sealed_bid.commitment == Self::sealed_commitment(bid, salt), "The bid and salt don't match your commitment");
+            ensure!(bid <= sealed_bid.deposit, "Your deposit doesn't cover this bid");
+            ensure!(bid >= auction.min_bid, "Your bid has to be at least the minimum bid");
+
+            sealed_bid.revealed = true;
+            <SealedBids<T>>::insert((kitty_id, sender.clone()), sealed_bid);
+
+            let has_bids = auction.high_bidder != auction.kitty_owner;
+            if !has_bids || bid > auction.high_bid {
+                let second_bid = if has_bids { auction.high_bid } else { second_bid };
+                auction.kind = AuctionKind::Sealed { commit_end, vickrey, second_bid };
+                auction.high_bid = bid;
+                auction.high_bidder = sender.clone();
+            } else if bid > second_bid {
+                auction.kind = AuctionKind::Sealed { commit_end, vickrey, second_bid: bid };
+            }
+            <KittyAuction<T>>::insert(kitty_id, auction);
+
+            Self::deposit_event(RawEvent::BidRevealed(sender, kitty_id, bid));
+
+            Ok(())
+        }
+
+        /// Returns a sealed bid deposit. Losing bids can be withdrawn as soon as they are revealed,
+        /// bids that were never revealed lose `UnrevealedForfeit` of their deposit once the auction ends.
+        fn withdraw_sealed_bid(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let sealed_bid = Self::sealed_bid_of((kitty_id, sender.clone())).ok_or("You have no bid for this cat")?;
+            // A bid from an earlier auction of this cat is judged by that auction, which is over
+            let auction = Self::auction_of(kitty_id).filter(|_| sealed_bid.round == Self::auction_round(kitty_id));
+            if let Some(ref auction) = auction {
+                ensure!(auction.high_bidder != sender, "You can't withdraw the highest bid");
+            }
+            if !sealed_bid.revealed {
+                let reveal_open = auction.map_or(false, |auction| <system::Module<T>>::block_number() < auction.expiry);
+                ensure!(!reveal_open, "Your bid can still be revealed");
+            }
+
+            Self::release_sealed_bid(kitty_id, sender, sealed_bid);
+
+            Ok(())
+        }
+
+        fn set_auction_reserve(origin, kitty_id: T::Hash, reserve: T::Balance) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
+            ensure!(auction.kitty_owner == sender, "You can't set a reserve for an auction you don't own");
+            ensure!(auction.kind == AuctionKind::English, "Only english auctions take a reserve price");
+            ensure!(<system::Module<T>>::block_number() < auction.expiry, "This auction is expired.");
+            ensure!(auction.high_bidder == auction.kitty_owner, "The reserve price can only be set before the first bid");
+
+            <ReservePrices<T>>::insert(kitty_id, reserve);
+
+            Ok(())
+        }
+
+        /// Cancels an auction. Once somebody bid, the owner pays the high bidder
+        /// `AuctionCancelPenalty` of the high bid.
+        fn cancel_auction(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
+            ensure!(auction.kitty_owner == sender, "You can't cancel an auction you don't own");
+            if let AuctionKind::Sealed { .. } = auction.kind {
+                return Err("Sealed auctions can't be cancelled");
+            }
+            ensure!(<system::Module<T>>::block_number() < auction.expiry, "This auction is expired.");
+
+            let penalty = if auction.high_bidder != auction.kitty_owner {
+                let penalty = Self::share_of(Self::auction_cancel_penalty(), auction.high_bid);
+                <balances::Module<T> as Currency<_>>::transfer(&sender, &auction.high_bidder, penalty)?;
+                penalty
+            } else {
+                Zero::zero()
+            };
+
+            Self::close_auction(&auction);
+            Self::refund_high_bid(&auction);
+
+            Self::deposit_event(RawEvent::AuctionCancelled(kitty_id, penalty));
+
+            Ok(())
+        }
+
+        fn buy_dutch(origin, kitty_id: T::Hash) -> Result {
+            let sender = ensure_signed(origin)?;
+
+            let auction = Self::auction_of(kitty_id).ok_or("No auction for this cat")?;
+            ensure!(auction.kitty_owner != sender, "You can't buy your own cat");
+
+            let now = <system::Module<T>>::block_number();
+            ensure!(now < auction.expiry, "This auction is expired.");
+
+            let price = Self::dutch_price(&auction, now).ok_or("This is not a dutch auction")?;
+
+            let (fee, royalty) = Self::pay_sale(&sender, &auction.kitty_owner, kitty_id, price)?;
+
+            Self::close_auction(&auction);
+            Self::do_transfer(auction.kitty_owner.clone(), sender.clone(), kitty_id)
+                .expect("`kitty_owner` owns the kitty while it is auctioned and the auction is closed; \
+                qed");
+
+            let mut kitty = Self::kitty(kitty_id);
+            kitty.price = <T::Balance as As<u64>>::sa(0);
+            <Kitties<T>>::insert(kitty_id, kitty);
+
+            Self::deposit_event(RawEvent::Bought(sender, auction.kitty_owner, kitty_id, price, fee, royalty));
+
+            Ok(())
+        }
+
+        fn on_finalize() {
+            let now = <system::Module<T>>::block_number();
+
+            Self::expire_offers(now);
+
+            if Self::auctions_expiring_count(now) > 0 {
+                let tail = <SettlementQueueTail<T>>::get();
+                <SettlementQueue<T>>::insert(tail, now);
+                <SettlementQueueTail<T>>::put(tail + 1);
+            }
+
+            Self::settle_auctions();
+        }
+    }
+}
+
+impl<T: Trait> Module<T> {
+    fn mint(to: T::AccountId, kitty_id: T::Hash, new_kitty: Kitty<T::Hash, T::Balance>) -> Result {
+        ensure!(!<KittyOwner<T>>::exists(kitty_id), "Kitty already exists");
+
+        let owned_kitty_count = Self::owned_kitty_count(&to);
+
+        let new_owned_kitty_count = owned_kitty_count.checked_add(1)
+            .ok_or("Overflow adding a new kitty to account balance")?;
+
+        let all_kitties_count = Self::all_kitties_count();
+
+        let new_all_kitties_count = all_kitties_count.checked_add(1)
+            .ok_or("Overflow adding a new kitty to total supply")?;
+
+        <Kitties<T>>::insert(kitty_id, new_kitty);
+        <KittyOwner<T>>::insert(kitty_id, &to);
+        <KittyCreator<T>>::insert(kitty_id, &to);
+
+        <AllKittiesArray<T>>::insert(all_kitties_count, kitty_id);
+        <AllKittiesCount<T>>::put(new_all_kitties_count);
+        <AllKittiesIndex<T>>::insert(kitty_id, all_kitties_count);
+
+        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count), kitty_id);
+        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count);
+        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count);
+
+        Self::deposit_event(RawEvent::Created(to, kitty_id));
+
+        Ok(())
+    }
+
+    fn ensure_can_breed(matron_id: T::Hash, sire_id: T::Hash) -> Result {
+        // Burned kitties keep their `Kitties` entry, only the owner tells whether one is alive
+        ensure!(Self::owner_of(matron_id).is_some(), "The matron does not exist");
+        ensure!(Self::owner_of(sire_id).is_some(), "The sire does not exist");
+        ensure!(matron_id != sire_id, "A kitty cannot breed with itself");
+        ensure!(!Self::is_parent_of(matron_id, sire_id) && !Self::is_parent_of(sire_id, matron_id),
+            "A kitty cannot breed with its parent or child");
+        ensure!(!Self::are_related(matron_id, sire_id, Self::inbreeding_depth()),
+            "These kitties share an ancestor within the inbreeding depth");
+
+        let now = <system::Module<T>>::block_number();
+        ensure!(Self::cooldown_end(matron_id) <= now, "The matron is still on breeding cooldown");
+        ensure!(Self::cooldown_end(sire_id) <= now, "The sire is still on breeding cooldown");
+
+        Ok(())
+    }
+
+    fn breed(owner: T::AccountId, matron_id: T::Hash, sire_id: T::Hash) -> rstd::result::Result<T::Hash, &'static str> {
+        Self::ensure_can_breed(matron_id, sire_id)?;
+
+        let now = <system::Module<T>>::block_number();
+        let nonce = <Nonce<T>>::get();
+        let random_hash = (<system::Module<T>>::random_seed(), &owner, nonce)
+            .using_encoded(<T as system::Trait>::Hashing::hash);
+
+        let matron = Self::kitty(matron_id);
+        let sire = Self::kitty(sire_id);
+
+        let mut final_dna = matron.dna;
+        genetics::inherit(matron.dna.as_ref(), sire.dna.as_ref(), random_hash.as_ref(),
+            Self::mutation_rate(), final_dna.as_mut());
+
+        let new_kitty = Kitty {
+            id: random_hash,
+            dna: final_dna,
+            price: <T::Balance as As<u64>>::sa(0),
+            gen: cmp::max(matron.gen, sire.gen) + 1,
+        };
+
+        Self::mint(owner.clone(), random_hash, new_kitty)?;
+
+        <Nonce<T>>::mutate(|n| *n += 1);
+
+        <KittyParents<T>>::insert(random_hash, (matron_id, sire_id));
+        Self::add_child(matron_id, random_hash);
+        Self::add_child(sire_id, random_hash);
+        <CooldownEnd<T>>::insert(matron_id, now + Self::cooldown_for(matron.gen));
+        <CooldownEnd<T>>::insert(sire_id, now + Self::cooldown_for(sire.gen));
+
+        Self::deposit_event(RawEvent::Bred(owner, matron_id, sire_id, random_hash));
+
+        Ok(random_hash)
+    }
+
+    fn is_parent_of(parent_id: T::Hash, kitty_id: T::Hash) -> bool {
+        match Self::parents_of(kitty_id) {
+            Some((matron_id, sire_id)) => matron_id == parent_id || sire_id == parent_id,
+            None => false,
+        }
+    }
+
+    fn add_child(parent_id: T::Hash, child_id: T::Hash) {
+        let children_count = Self::children_count(parent_id);
+        <KittyChildrenArray<T>>::insert((parent_id, children_count), child_id);
+        <KittyChildrenCount<T>>::insert(parent_id, children_count + 1);
+    }
+
+    /// The children of `kitty_id`, in the order they were bred.
+    pub fn children_of(kitty_id: T::Hash) -> Vec<T::Hash> {
+        (0..Self::children_count(kitty_id))
+            .map(|i| Self::child_of_kitty_by_index((kitty_id, i)))
+            .collect()
+    }
+
+    /// The ancestors of `kitty_id` up to `depth` generations back, closest generation first.
+    /// A kitty reached through several lines appears once per line.
+    pub fn ancestors(kitty_id: T::Hash, depth: u32) -> Vec<T::Hash> {
+        let mut ancestors = Vec::new();
+        let mut generation = vec![kitty_id];
+
+        for _ in 0..depth {
+            let parents: Vec<T::Hash> = generation.iter()
+                .filter_map(|id| Self::parents_of(id))
+                .flat_map(|(matron_id, sire_id)| vec![matron_id, sire_id])
+                .collect();
+            if parents.is_empty() {
+                break;
+            }
+            ancestors.extend(parents.iter().cloned());
+            generation = parents;
+        }
+
+        ancestors
+    }
+
+    /// The descendants of `kitty_id` up to `depth` generations down, closest generation first.
+    pub fn descendants(kitty_id: T::Hash, depth: u32) -> Vec<T::Hash> {
+        let mut descendants = Vec::new();
+        let mut generation = vec![kitty_id];
+
+        for _ in 0..depth {
+            let children: Vec<T::Hash> = generation.iter()
+                .flat_map(|id| Self::children_of(*id))
+                .collect();
+            if children.is_empty() {
+                break;
+            }
+            descendants.extend(children.iter().cloned());
+            generation = children;
+        }
+
+        descendants
+    }
+
+    fn are_related(kitty_id_1: T::Hash, kitty_id_2: T::Hash, depth: u32) -> bool {
+        let mut line_1 = Self::ancestors(kitty_id_1, depth);
+        line_1.push(kitty_id_1);
+        let mut line_2 = Self::ancestors(kitty_id_2, depth);
+        line_2.push(kitty_id_2);
+
+        line_1.iter().any(|id| line_2.contains(id))
+    }
+
+    fn cooldown_for(gen: u64) -> T::BlockNumber {
+        let multiplier = cmp::min(gen.saturating_add(1), MAX_COOLDOWN_MULTIPLIER);
+        Self::breeding_cooldown() * T::BlockNumber::sa(multiplier)
+    }
+
+    fn is_approved_or_owner(who: &T::AccountId, owner: &T::AccountId, kitty_id: T::Hash) -> bool {
+        who == owner
+            || Self::approved_of(kitty_id).as_ref() == Some(who)
+            || Self::is_approved_for_all((owner.clone(), who.clone()))
+    }
+
+    fn burn(owner: T::AccountId, kitty_id: T::Hash) -> Result {
+        let current_owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+
+        ensure!(current_owner == owner, "'owner' account does not own this kitty");
+
+        Self::ensure_not_auctioned(kitty_id)?;
+
+        let new_owned_kitty_count = Self::owned_kitty_count(&owner).checked_sub(1)
+            .ok_or("Burn causes underflow of 'owner' kitty balance")?;
+
+        let new_all_kitties_count = Self::all_kitties_count().checked_sub(1)
+            .ok_or("Burn causes underflow of total supply")?;
+
+        let kitty_index = <OwnedKittiesIndex<T>>::get(kitty_id);
+        if kitty_index != new_owned_kitty_count {
+            let last_kitty_id = <OwnedKittiesArray<T>>::get((owner.clone(), new_owned_kitty_count));
+            <OwnedKittiesArray<T>>::insert((owner.clone(), kitty_index), last_kitty_id);
+            <OwnedKittiesIndex<T>>::insert(last_kitty_id, kitty_index);
+        }
+
+        <OwnedKittiesArray<T>>::remove((owner.clone(), new_owned_kitty_count));
+        <OwnedKittiesIndex<T>>::remove(kitty_id);
+        <OwnedKittiesCount<T>>::insert(&owner, new_owned_kitty_count);
+
+        let all_kitties_index = <AllKittiesIndex<T>>::get(kitty_id);
+        if all_kitties_index != new_all_kitties_count {
+            let last_kitty_id = <AllKittiesArray<T>>::get(new_all_kitties_count);
+            <AllKittiesArray<T>>::insert(all_kitties_index, last_kitty_id);
+            <AllKittiesIndex<T>>::insert(last_kitty_id, all_kitties_index);
+        }
+
+        <AllKittiesArray<T>>::remove(new_all_kitties_count);
+        <AllKittiesIndex<T>>::remove(kitty_id);
+        <AllKittiesCount<T>>::put(new_all_kitties_count);
+
+        // The kitty itself stays in `Kitties` so the lineage of its descendants can still be walked.
+        <KittyOwner<T>>::remove(kitty_id);
+        <Kitties<T>>::mutate(kitty_id, |kitty| kitty.price = <T::Balance as As<u64>>::sa(0));
+        <KittyApprovals<T>>::remove(kitty_id);
+        <SireAllowedTo<T>>::remove(kitty_id);
+        <SiringOffers<T>>::remove(kitty_id);
+
+        Self::deposit_event(RawEvent::Burned(owner, kitty_id));
+
+        Ok(())
+    }
+
+    /// Pays `price` for `kitty_id` out of the free balance of `buyer`. The marketplace fee goes to
+    /// the fee destination, the creator royalty to the account that minted or bred the kitty and
+    /// the rest to `seller`. Returns the fee and the royalty. Nothing is paid unless every
+    /// transfer can be made.
+    fn pay_sale(buyer: &T::AccountId, seller: &T::AccountId, kitty_id: T::Hash, price: T::Balance)
+        -> rstd::result::Result<(T::Balance, T::Balance), &'static str>
+    {
+        let (legs, fee, royalty) = Self::sale_legs(buyer, seller, &[kitty_id], price)?;
+        Self::ensure_can_pay(buyer, &legs, Zero::zero())?;
+        Self::pay_legs(buyer, legs)?;
+
+        Ok((fee, royalty))
+    }
+
+    /// Splits `price` for `kitties` into the transfers `buyer` makes for a sale, one per receiving
+    /// account, together with the fee and the royalty. Every kitty of a lot carries an equal part
+    /// of the price, its creator is paid the royalty on that part.
+    fn sale_legs(buyer: &T::AccountId, seller: &T::AccountId, kitties: &[T::Hash], price: T::Balance)
+        -> rstd::result::Result<(Vec<(T::AccountId, T::Balance)>, T::Balance, T::Balance), &'static str>
+    {
+        let fee = Self::share_of(Self::marketplace_fee(), price);
+        let part = price / <T::Balance as As<u64>>::sa(kitties.len() as u64);
+        let royalties: Vec<(T::AccountId, T::Balance)> = kitties.iter()
+            .filter_map(|kitty_id| Self::creator_of(kitty_id).filter(|creator| creator != seller))
+            .map(|creator| (creator, Self::share_of(Self::creator_royalty(), part)))
+            .collect();
+        let royalty = royalties.iter().fold(Zero::zero(), |total: T::Balance, (_, amount)| total + *amount);
+
+        let seller_share = price.checked_sub(&fee)
+            .and_then(|rest| rest.checked_sub(&royalty))
+            .ok_or("The marketplace fee and creator royalty exceed the price")?;
+
+        let shares = rstd::iter::once((Self::fee_destination(), fee))
+            .chain(royalties)
+            .chain(rstd::iter::once((seller.clone(), seller_share)));
+
+        // Shares owed to the buyer itself stay where they are
+        let mut legs: Vec<(T::AccountId, T::Balance)> = Vec::new();
+        for (who, amount) in shares {
+            if amount.is_zero() || who == *buyer {
+                continue;
+            }
+            match legs.iter_mut().find(|(leg, _)| *leg == who) {
+                Some(leg) => leg.1 += amount,
+                None => legs.push((who, amount)),
+            }
+        }
+
+        Ok((legs, fee, royalty))
+    }
+
+    /// Checks every transfer of `legs` up front, `Currency::transfer` has no rollback. `pending`
+    /// is a reserve of `buyer` that is released right before paying.
+    ///
+    /// A free balance that drops below the existential deposit is wiped, so the buyer has to stay
+    /// above it once `pending` is released and after every transfer but the last.
+    fn ensure_can_pay(buyer: &T::AccountId, legs: &[(T::AccountId, T::Balance)], pending: T::Balance) -> Result {
+        let existential_deposit = <balances::Module<T>>::existential_deposit();
+        let mut remaining = <balances::Module<T>>::free_balance(buyer).checked_add(&pending)
+            .ok_or("The payment for this sale overflows")?;
+        ensure!(remaining >= existential_deposit, "You don't have enough free balance for this purchase");
+
+        for (i, (who, amount)) in legs.iter().enumerate() {
+            let balance = <balances::Module<T>>::free_balance(who);
+            let transfer_fee = if balance.is_zero() {
+                ensure!(*amount >= existential_deposit, "A share of this sale is too low to create the receiving account");
+                <balances::Module<T>>::creation_fee()
+            } else {
+                <balances::Module<T>>::transfer_fee()
+            };
+            ensure!(balance.checked_add(amount).is_some(), "A share of this sale overflows the receiving balance");
+
+            remaining = amount.checked_add(&transfer_fee)
+                .and_then(|liability| remaining.checked_sub(&liability))
+                .ok_or("You don't have enough free balance for this purchase")?;
+            if i + 1 < legs.len() {
+                ensure!(remaining >= existential_deposit,
+                    "This purchase would leave your balance below the existential deposit");
+            }
+            <balances::Module<T> as Currency<_>>::ensure_can_withdraw(buyer, *amount, WithdrawReason::Transfer, remaining)?;
+        }
+
+        Ok(())
+    }
+
+    fn pay_legs(buyer: &T::AccountId, legs: Vec<(T::AccountId, T::Balance)>) -> Result {
+        for (who, amount) in legs {
+            <balances::Module<T> as Currency<_>>::transfer(buyer, &who, amount)?;
+        }
+
+        Ok(())
+    }
+
+    fn parts(rate: Permill) -> u64 {
+        rate * 1_000_000u64
+    }
+
+    // `Permill * Balance` goes through `u64` and saturates for large balances, so split the
+    // multiplication around the million instead.
+    fn share_of(rate: Permill, amount: T::Balance) -> T::Balance {
+        let million = <T::Balance as As<u64>>::sa(1_000_000);
+        let parts = <T::Balance as As<u64>>::sa(Self::parts(rate));
+        amount / million * parts + amount % million * parts / million
+    }
+
+    fn remove_offer(kitty_id: T::Hash, buyer: T::AccountId, expiry: T::BlockNumber) {
+        <Offers<T>>::remove((kitty_id, buyer.clone()));
+        <OffersExpireAt<T>>::mutate(expiry, |offers| offers.retain(|offer| *offer != (kitty_id, buyer.clone())));
+    }
+
+    fn expire_offers(now: T::BlockNumber) {
+        for (kitty_id, buyer) in <OffersExpireAt<T>>::take(now) {
+            if let Some(offer) = <Offers<T>>::take((kitty_id, buyer.clone())) {
+                let _ = <balances::Module<T>>::unreserve(&buyer, offer.amount);
+
+                Self::deposit_event(RawEvent::OfferExpired(buyer, kitty_id));
+            }
+        }
+    }
+
+    fn open_auction(owner: T::AccountId, kitty_id: T::Hash, min_bid: T::Balance, expiry: T::BlockNumber,
+        kind: AuctionKind<T::Balance, T::BlockNumber>, lot: Vec<T::Hash>) -> Result
+    {
+        Self::ensure_not_auctioned(kitty_id)?;
+        for member in &lot {
+            Self::ensure_not_auctioned(*member)?;
+        }
+
+        let new_auction = Auction {
+            kitty_id,
+            kitty_owner: owner.clone(),
+            expiry,
+            min_bid,
+            high_bid: min_bid,
+            high_bidder: owner.clone(),
+            kind: kind.clone(),
+            lot,
+        };
+
+        Self::lock_auction(&new_auction);
+        <AuctionRound<T>>::mutate(kitty_id, |round| *round += 1);
+
+        let active_count = Self::active_auctions_count();
+        <ActiveAuctionsArray<T>>::insert(active_count, kitty_id);
+        <ActiveAuctionsCount<T>>::put(active_count + 1);
+        <ActiveAuctionsIndex<T>>::insert(kitty_id, active_count);
+
+        let seller_count = Self::seller_auctions_count(&owner);
+        <SellerAuctionsArray<T>>::insert((owner.clone(), seller_count), kitty_id);
+        <SellerAuctionsCount<T>>::insert(&owner, seller_count + 1);
+        <SellerAuctionsIndex<T>>::insert(kitty_id, seller_count);
+
+        // Sealed auctions come back at the end of the commit phase to open the reveal phase
+        match kind {
+            AuctionKind::Sealed { commit_end, .. } => Self::schedule_auction(kitty_id, commit_end),
+            _ => Self::schedule_auction(kitty_id, expiry),
+        }
+
+        match kind {
+            AuctionKind::English if !new_auction.lot.is_empty() =>
+                Self::deposit_event(RawEvent::LotAuctionCreated(kitty_id, new_auction.lot, min_bid, expiry)),
+            AuctionKind::English => Self::deposit_event(RawEvent::AuctionCreated(kitty_id, min_bid, expiry)),
+            AuctionKind::Dutch { start_price, end_price, .. } =>
+                Self::deposit_event(RawEvent::DutchAuctionCreated(kitty_id, start_price, end_price, expiry)),
+            AuctionKind::Sealed { commit_end, .. } =>
+                Self::deposit_event(RawEvent::SealedAuctionCreated(kitty_id, min_bid, commit_end, expiry)),
+        }
+
+        Ok(())
+    }
+
+    /// The lowest bid `auction` accepts. The first bid only has to beat `min_bid`, later ones
+    /// have to beat the high bid by the larger of the absolute and relative minimum increment.
+    pub fn min_next_bid(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) -> T::Balance {
+        let one = <T::Balance as As<u64>>::sa(1);
+        if auction.high_bidder == auction.kitty_owner {
+            return auction.high_bid + one;
+        }
+
+        let increment = cmp::max(Self::min_bid_increment(), Self::share_of(Self::min_bid_increment_permill(), auction.high_bid));
+        auction.high_bid + cmp::max(increment, one)
+    }
+
+    fn schedule_auction(kitty_id: T::Hash, expiry: T::BlockNumber) {
+        let count = Self::auctions_expiring_count(expiry);
+
+        <AuctionsExpiring<T>>::insert((expiry, count), kitty_id);
+        <AuctionsExpiringCount<T>>::insert(expiry, count + 1);
+        <AuctionSlot<T>>::insert(kitty_id, (expiry, count));
+    }
+
+    fn unschedule_auction(kitty_id: T::Hash) {
+        let (expiry, index) = match <AuctionSlot<T>>::take(kitty_id) {
+            Some(slot) => slot,
+            None => return,
+        };
+        let last_index = Self::auctions_expiring_count(expiry).saturating_sub(1);
+
+        if index != last_index {
+            let last_kitty_id = <AuctionsExpiring<T>>::get((expiry, last_index));
+            <AuctionsExpiring<T>>::insert((expiry, index), last_kitty_id);
+            <AuctionSlot<T>>::insert(last_kitty_id, (expiry, index));
+        }
+
+        <AuctionsExpiring<T>>::remove((expiry, last_index));
+        if last_index == 0 {
+            <AuctionsExpiringCount<T>>::remove(expiry);
+        } else {
+            <AuctionsExpiringCount<T>>::insert(expiry, last_index);
+        }
+    }
+
+    fn close_auction(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) {
+        Self::unschedule_auction(auction.kitty_id);
+        Self::end_auction(auction);
+    }
+
+    fn refund_high_bid(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) {
+        if auction.high_bidder != auction.kitty_owner {
+            let escrow = Self::take_escrow(auction);
+            let _ = <balances::Module<T>>::unreserve(&auction.high_bidder, escrow);
+        }
+    }
+
+    /// Returns a sealed bid deposit, less `UnrevealedForfeit` when the bid was never revealed.
+    /// Unrevealed bids must only be released once their reveal phase is over.
+    fn release_sealed_bid(kitty_id: T::Hash, bidder: T::AccountId, sealed_bid: SealedBid<T::Hash, T::Balance>) {
+        let forfeit = if sealed_bid.revealed {
+            Zero::zero()
+        } else {
+            Self::share_of(Self::unrevealed_forfeit(), sealed_bid.deposit)
+        };
+
+        <SealedBids<T>>::remove((kitty_id, bidder.clone()));
+        Self::unindex_bid(kitty_id, &bidder);
+        if !forfeit.is_zero() {
+            let _ = <balances::Module<T>>::slash_reserved(&bidder, forfeit);
+            Self::deposit_event(RawEvent::DepositForfeited(bidder.clone(), kitty_id, forfeit));
+        }
+        let _ = <balances::Module<T>>::unreserve(&bidder, sealed_bid.deposit - forfeit);
+
+        Self::deposit_event(RawEvent::BidWithdrawn(bidder, kitty_id, sealed_bid.deposit - forfeit));
+    }
+
+    /// What the high bidder has reserved for `auction`.
+    fn escrow_of(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) -> T::Balance {
+        let key = (auction.kitty_id, auction.high_bidder.clone());
+        match auction.kind {
+            AuctionKind::Sealed { .. } => Self::sealed_bid_of(key)
+                .filter(|bid| bid.round == Self::auction_round(auction.kitty_id))
+                .map(|bid| bid.deposit)
+                .unwrap_or_default(),
+            _ => Self::bid_of(key)
+                .and_then(|(round, amount)| if round == Self::auction_round(auction.kitty_id) { Some(amount) } else { None })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn take_escrow(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) -> T::Balance {
+        let escrow = Self::escrow_of(auction);
+        let key = (auction.kitty_id, auction.high_bidder.clone());
+        match auction.kind {
+            AuctionKind::Sealed { .. } => <SealedBids<T>>::remove(key),
+            _ => <Bids<T>>::remove(key),
+        }
+        Self::unindex_bid(auction.kitty_id, &auction.high_bidder);
+        escrow
+    }
+
+    /// What the winner of `auction` pays, the second highest bid in a vickrey auction and the
+    /// highest bid otherwise.
+    pub fn sale_price(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) -> T::Balance {
+        match auction.kind {
+            AuctionKind::Sealed { vickrey: true, second_bid, .. } => second_bid,
+            _ => auction.high_bid,
+        }
+    }
+
+    /// The commitment `commit_bid` expects for a sealed `bid` hidden with `salt`.
+    pub fn sealed_commitment(bid: T::Balance, salt: T::Hash) -> T::Hash {
+        (bid, salt).using_encoded(<T as system::Trait>::Hashing::hash)
+    }
+
+    /// Settles up to `MAX_SETTLEMENTS_PER_BLOCK` expired auctions, oldest expiry block first.
+    fn settle_auctions() {
+        let mut settled = 0;
+
+        while settled < MAX_SETTLEMENTS_PER_BLOCK {
+            let head = <SettlementQueueHead<T>>::get();
+            if head == <SettlementQueueTail<T>>::get() {
+                break;
+            }
+
+            let expiry = <SettlementQueue<T>>::get(head);
+            let count = Self::auctions_expiring_count(expiry);
+            if count == 0 {
+                <SettlementQueue<T>>::remove(head);
+                <SettlementQueueHead<T>>::put(head + 1);
+                continue;
+            }
+
+            let kitty_id = <AuctionsExpiring<T>>::get((expiry, count - 1));
+            Self::unschedule_auction(kitty_id);
+            Self::settle_auction(kitty_id);
+            settled += 1;
+        }
+    }
+
+    fn settle_auction(kitty_id: T::Hash) {
+        let auction = match Self::auction_of(kitty_id) {
+            Some(auction) => auction,
+            None => return,
+        };
+
+        if let AuctionKind::Sealed { .. } = auction.kind {
+            if <system::Module<T>>::block_number() < auction.expiry {
+                Self::schedule_auction(kitty_id, auction.expiry);
+                Self::deposit_event(RawEvent::RevealPhaseStarted(kitty_id));
+                return;
+            }
+        }
+
+        if auction.kitty_owner == auction.high_bidder {
+            Self::end_auction(&auction);
+            Self::deposit_event(RawEvent::AuctionEndedWithoutBids(kitty_id));
+            return;
+        }
+
+        if <ReservePrices<T>>::get(kitty_id).map_or(false, |reserve| auction.high_bid < reserve) {
+            Self::end_auction(&auction);
+            Self::refund_high_bid(&auction);
+            Self::deposit_event(RawEvent::AuctionReserveNotMet(kitty_id, auction.high_bid));
+            return;
+        }
+
+        match Self::try_settle(&auction) {
+            Ok((fee, royalty)) => {
+                Self::end_auction(&auction);
+                Self::deposit_event(RawEvent::AuctionFinalized(kitty_id, Self::sale_price(&auction), auction.expiry, fee, royalty));
+            },
+            Err(reason) => {
+                Self::deposit_event(RawEvent::AuctionFailed(kitty_id, reason.as_bytes().to_vec()));
+
+                // `expiry` stays in the past so the auction takes no bids while it waits for the retry
+                let attempts = Self::settlement_attempts(kitty_id) + 1;
+                if attempts < MAX_SETTLEMENT_ATTEMPTS {
+                    <SettlementAttempts<T>>::insert(kitty_id, attempts);
+                    let retry_at = <system::Module<T>>::block_number() + T::BlockNumber::sa(SETTLEMENT_RETRY_DELAY);
+                    Self::schedule_auction(kitty_id, retry_at);
+                } else {
+                    Self::end_auction(&auction);
+                    Self::refund_high_bid(&auction);
+                }
+            },
+        }
+    }
+
+    /// Moves the kitty, or every kitty of the lot, to the high bidder and pays for it out of the
+    /// reserved high bid. If this fails the kitties, the auction and the reserved bid are left as
+    /// they were.
+    fn try_settle(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>)
+        -> rstd::result::Result<(T::Balance, T::Balance), &'static str>
+    {
+        let kitty_id = auction.kitty_id;
+        let owner = auction.kitty_owner.clone();
+        let bidder = auction.high_bidder.clone();
+        let escrow = Self::escrow_of(auction);
+        let price = Self::sale_price(auction);
+        let kitties: Vec<T::Hash> = rstd::iter::once(kitty_id).chain(auction.lot.iter().cloned()).collect();
+
+        for id in &kitties {
+            ensure!(Self::owner_of(id).as_ref() == Some(&owner), "The seller no longer owns this kitty");
+        }
+        ensure!(escrow >= price, "The high bid is not reserved");
+        ensure!(<balances::Module<T>>::reserved_balance(&bidder) >= escrow, "The high bid is not reserved");
+        let (legs, fee, royalty) = Self::sale_legs(&bidder, &owner, &kitties, price)?;
+        Self::ensure_can_pay(&bidder, &legs, escrow)?;
+
+        Self::unlock_auction(auction);
+        for (moved, id) in kitties.iter().enumerate() {
+            if let Err(e) = Self::do_transfer(owner.clone(), bidder.clone(), *id) {
+                Self::return_kitties(&bidder, &owner, &kitties[..moved]);
+                Self::lock_auction(auction);
+                return Err(e);
+            }
+        }
+
+        // The payment was checked above, with the escrow counted in. Should a transfer still fail,
+        // the ones already made can't be undone, but the kitties go back and the failure is
+        // reported and retried like any other.
+        let _ = <balances::Module<T>>::unreserve(&bidder, escrow);
+        if let Err(e) = Self::pay_legs(&bidder, legs) {
+            let _ = <balances::Module<T>>::reserve(&bidder, escrow);
+            Self::return_kitties(&bidder, &owner, &kitties);
+            Self::lock_auction(auction);
+            return Err(e);
+        }
+        Self::take_escrow(auction);
+
+        Ok((fee, royalty))
+    }
+
+    fn return_kitties(from: &T::AccountId, to: &T::AccountId, kitties: &[T::Hash]) {
+        for id in kitties {
+            // Moving a kitty straight back cannot fail, the forward move just succeeded
+            let _ = Self::do_transfer(from.clone(), to.clone(), *id);
+        }
+    }
+
+    fn lock_auction(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) {
+        <KittyAuction<T>>::insert(auction.kitty_id, auction);
+        for member in &auction.lot {
+            <LotLeader<T>>::insert(member, auction.kitty_id);
+        }
+    }
+
+    fn unlock_auction(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) {
+        <KittyAuction<T>>::remove(auction.kitty_id);
+        for member in &auction.lot {
+            <LotLeader<T>>::remove(member);
+        }
+    }
+
+    /// Kitties in an auction, either on their own or as part of a lot, cannot change hands.
+    fn ensure_not_auctioned(kitty_id: T::Hash) -> Result {
+        ensure!(!<KittyAuction<T>>::exists(kitty_id) && !<LotLeader<T>>::exists(kitty_id), "This kitty has an open auction.");
+
+        Ok(())
+    }
+
+    fn end_auction(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>) {
+        let kitty_id = auction.kitty_id;
+
+        Self::unlock_auction(auction);
+        <ReservePrices<T>>::remove(kitty_id);
+        <SettlementAttempts<T>>::remove(kitty_id);
+
+        let active_index = <ActiveAuctionsIndex<T>>::take(kitty_id);
+        let last_active_index = Self::active_auctions_count().saturating_sub(1);
+        if active_index != last_active_index {
+            let last_kitty_id = <ActiveAuctionsArray<T>>::get(last_active_index);
+            <ActiveAuctionsArray<T>>::insert(active_index, last_kitty_id);
+            <ActiveAuctionsIndex<T>>::insert(last_kitty_id, active_index);
+        }
+        <ActiveAuctionsArray<T>>::remove(last_active_index);
+        <ActiveAuctionsCount<T>>::put(last_active_index);
+
+        let seller = auction.kitty_owner.clone();
+        let seller_index = <SellerAuctionsIndex<T>>::take(kitty_id);
+        let last_seller_index = Self::seller_auctions_count(&seller).saturating_sub(1);
+        if seller_index != last_seller_index {
+            let last_kitty_id = <SellerAuctionsArray<T>>::get((seller.clone(), last_seller_index));
+            <SellerAuctionsArray<T>>::insert((seller.clone(), seller_index), last_kitty_id);
+            <SellerAuctionsIndex<T>>::insert(last_kitty_id, seller_index);
+        }
+        <SellerAuctionsArray<T>>::remove((seller.clone(), last_seller_index));
+        <SellerAuctionsCount<T>>::insert(&seller, last_seller_index);
+    }
+
+    /// Records that `bidder` has a bid reserved for `kitty_id`.
+    fn index_bid(kitty_id: T::Hash, bidder: &T::AccountId) {
+        if <BidderAuctionsIndex<T>>::exists((kitty_id, bidder.clone())) {
+            return;
+        }
+
+        let count = Self::bidder_auctions_count(bidder);
+        <BidderAuctionsArray<T>>::insert((bidder.clone(), count), kitty_id);
+        <BidderAuctionsCount<T>>::insert(bidder, count + 1);
+        <BidderAuctionsIndex<T>>::insert((kitty_id, bidder.clone()), count);
+    }
+
+    /// Forgets `kitty_id` for `bidder` once neither an open nor a sealed bid is left.
+    fn unindex_bid(kitty_id: T::Hash, bidder: &T::AccountId) {
+        let key = (kitty_id, bidder.clone());
+        if <Bids<T>>::exists(key.clone()) || <SealedBids<T>>::exists(key.clone()) || !<BidderAuctionsIndex<T>>::exists(key.clone()) {
+            return;
+        }
+
+        let index = <BidderAuctionsIndex<T>>::take(key);
+        let last_index = Self::bidder_auctions_count(bidder).saturating_sub(1);
+        if index != last_index {
+            let last_kitty_id = <BidderAuctionsArray<T>>::get((bidder.clone(), last_index));
+            <BidderAuctionsArray<T>>::insert((bidder.clone(), index), last_kitty_id);
+            <BidderAuctionsIndex<T>>::insert((last_kitty_id, bidder.clone()), index);
+        }
+        <BidderAuctionsArray<T>>::remove((bidder.clone(), last_index));
+        <BidderAuctionsCount<T>>::insert(bidder, last_index);
+    }
+
+    /// `kitty_id` with its owner and breeding state, `None` if it doesn't exist.
+    pub fn kitty_info(kitty_id: T::Hash) -> Option<KittyInfo<T::AccountId, T::Hash, T::Balance, T::BlockNumber>> {
+        let owner = Self::owner_of(kitty_id)?;
+        let kitty = Self::kitty(kitty_id);
+        let genome = genetics::Genome::decode(kitty.dna.as_ref());
+        Some(KittyInfo {
+            traits: genetics::GeneSlot::ALL.iter().map(|slot| genome.trait_of(*slot)).collect(),
+            kitty,
+            owner,
+            creator: Self::creator_of(kitty_id),
+            parents: Self::parents_of(kitty_id),
+            children: Self::children_count(kitty_id),
+            cooldown_end: Self::cooldown_end(kitty_id),
+            siring_fee: Self::siring_offer_of(kitty_id),
+            in_auction: Self::ensure_not_auctioned(kitty_id).is_err(),
+        })
+    }
+
+    /// Up to `count` kitty ids from index `start` of `AllKittiesArray`, and the number of kitties.
+    pub fn kitties_page(start: u64, count: u64) -> (Vec<T::Hash>, u64) {
+        let total = Self::all_kitties_count();
+        let end = cmp::min(total, start.saturating_add(count));
+        ((start..end).map(Self::kitty_by_index).collect(), total)
+    }
+
+    /// Up to `count` ids of `owner`'s kitties from index `start`, and the number it owns.
+    pub fn owned_kitties_page(owner: &T::AccountId, start: u64, count: u64) -> (Vec<T::Hash>, u64) {
+        let total = Self::owned_kitty_count(owner);
+        let end = cmp::min(total, start.saturating_add(count));
+        ((start..end).map(|i| Self::kitty_of_owner_by_index((owner.clone(), i))).collect(), total)
+    }
+
+    /// The auction `kitty_id` is sold in, on its own or as part of a lot.
+    pub fn auction_for(kitty_id: T::Hash) -> Option<Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>> {
+        Self::auction_of(Self::lot_leader_of(kitty_id).unwrap_or(kitty_id))
+    }
+
+    /// What breeding `matron_id` with `sire_id` would result in at the current block.
+    pub fn quote_breed(matron_id: T::Hash, sire_id: T::Hash) -> BreedQuote<T::Balance, T::BlockNumber> {
+        let matron = Self::kitty(matron_id);
+        let sire = Self::kitty(sire_id);
+        BreedQuote {
+            error: Self::ensure_can_breed(matron_id, sire_id).err().map(|e| e.as_bytes().to_vec()),
+            siring_fee: Self::siring_offer_of(sire_id),
+            child_gen: cmp::max(matron.gen, sire.gen) + 1,
+            matron_cooldown: Self::cooldown_for(matron.gen),
+            sire_cooldown: Self::cooldown_for(sire.gen),
+        }
+    }
+
+    /// All open auctions, including expired ones that still wait for settlement.
+    pub fn active_auctions() -> Vec<Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>> {
+        (0..Self::active_auctions_count())
+            .filter_map(|i| Self::auction_of(Self::active_auction_by_index(i)))
+            .collect()
+    }
+
+    /// The expiry bucket and index every open auction is scheduled in, by kitty id.
+    pub fn auction_slots() -> Vec<(T::Hash, T::BlockNumber, u32)> {
+        (0..Self::active_auctions_count())
+            .map(Self::active_auction_by_index)
+            .filter_map(|kitty_id| Self::auction_slot(kitty_id).map(|(block, index)| (kitty_id, block, index)))
+            .collect()
+    }
+
+    /// The open auctions `seller` put their kitties in.
+    pub fn auctions_by_seller(seller: &T::AccountId) -> Vec<Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>> {
+        (0..Self::seller_auctions_count(seller))
+            .filter_map(|i| Self::auction_of(Self::seller_auction_by_index((seller.clone(), i))))
+            .collect()
+    }
+
+    /// The kitties `bidder` has a bid reserved for, with the reserved amount. Sealed bids report
+    /// their deposit.
+    pub fn auctions_by_bidder(bidder: &T::AccountId) -> Vec<(T::Hash, T::Balance)> {
+        (0..Self::bidder_auctions_count(bidder))
+            .map(|i| {
+                let kitty_id = Self::bidder_auction_by_index((bidder.clone(), i));
+                let key = (kitty_id, bidder.clone());
+                let bid = Self::bid_of(key.clone()).map(|(_, amount)| amount).unwrap_or_default()
+                    + Self::sealed_bid_of(key).map(|bid| bid.deposit).unwrap_or_default();
+                (kitty_id, bid)
+            })
+            .collect()
+    }
+
+    /// The price of a dutch auction at block `now`, `None` for other auctions.
+    pub fn dutch_price(auction: &Auction<T::Hash, T::Balance, T::BlockNumber, T::AccountId>, now: T::BlockNumber)
+        -> Option<T::Balance>
+    {
+        match auction.kind {
+            AuctionKind::Dutch { start_price, end_price, start } => {
+                let duration = (auction.expiry - start).as_();
+                let elapsed = cmp::min(now.as_().saturating_sub(start.as_()), duration);
+                let range = start_price - end_price;
+                let elapsed = <T::Balance as As<u64>>::sa(elapsed);
+                let duration = <T::Balance as As<u64>>::sa(duration);
+                // `range * elapsed / duration` split up so prices near the top of the balance type
+                // don't overflow the product
+                let drop = range / duration * elapsed + (range % duration).checked_mul(&elapsed)
+                    .map(|scaled| scaled / duration)
+                    .unwrap_or_else(Zero::zero);
+                Some(start_price - drop)
+            },
+            _ => None,
+        }
+    }
+
+    fn ensure_batch_size(len: usize) -> Result {
+        ensure!(len > 0, "The batch is empty");
+        ensure!(len <= Self::max_batch_size() as usize, "The batch is larger than the maximum batch size");
+
+        Ok(())
+    }
+
+    fn ensure_can_transfer(from: &T::AccountId, to: &T::AccountId, kitty_id: T::Hash) -> Result {
+        let owner = Self::owner_of(kitty_id).ok_or("No owner for this kitty")?;
+
+        ensure!(owner == *from, "'from' account does not own this kitty");
+        // Moving a kitty to its owner would count it twice in the owner's indexes
+        ensure!(*from != *to, "You cannot transfer a kitty to yourself");
+
+        Self::ensure_not_auctioned(kitty_id)?;
+
+        Ok(())
+    }
+
+    fn do_transfer(from: T::AccountId, to: T::AccountId, kitty_id: T::Hash) -> Result {
+        Self::ensure_can_transfer(&from, &to, kitty_id)?;
+
+        let owned_kitty_count_from = Self::owned_kitty_count(&from);
+        let owned_kitty_count_to = Self::owned_kitty_count(&to);
+
+        let new_owned_kitty_count_to = owned_kitty_count_to.checked_add(1)
+            .ok_or("Transfer causes overflow of 'to' kitty balance")?;
+
+        let new_owned_kitty_count_from = owned_kitty_count_from.checked_sub(1)
+            .ok_or("Transfer causes underflow of 'from' kitty balance")?;
+
+        let kitty_index = <OwnedKittiesIndex<T>>::get(kitty_id);
+        if kitty_index != new_owned_kitty_count_from {
+            let last_kitty_id = <OwnedKittiesArray<T>>::get((from.clone(), new_owned_kitty_count_from));
+            <OwnedKittiesArray<T>>::insert((from.clone(), kitty_index), last_kitty_id);
+            <OwnedKittiesIndex<T>>::insert(last_kitty_id, kitty_index);
+        }
+
+        <KittyOwner<T>>::insert(&kitty_id, &to);
+        <OwnedKittiesIndex<T>>::insert(kitty_id, owned_kitty_count_to);
+        <KittyApprovals<T>>::remove(kitty_id);
+        <SireAllowedTo<T>>::remove(kitty_id);
+        <SiringOffers<T>>::remove(kitty_id);
+
+        <OwnedKittiesArray<T>>::remove((from.clone(), new_owned_kitty_count_from));
+        <OwnedKittiesArray<T>>::insert((to.clone(), owned_kitty_count_to), kitty_id);
+
+        <OwnedKittiesCount<T>>::insert(&from, new_owned_kitty_count_from);
+        <OwnedKittiesCount<T>>::insert(&to, new_owned_kitty_count_to);
+
+        Self::deposit_event(RawEvent::Transferred(from, to, kitty_id));
+
+        Ok(())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use support::{impl_outer_origin, assert_ok, assert_noop};
+    use runtime_io::{with_externalities, TestExternalities};
+    use primitives::{H256, Blake2Hasher};
+    use runtime_primitives::{
+        BuildStorage,
+        traits::{BlakeTwo256, IdentityLookup, OnFinalize},
+        testing::{Digest, DigestItem, Header}
+    };
+
+    impl_outer_origin! {
+        pub enum Origin for KittiesTest {}
+    }
+
+    #[derive(Clone, Eq, PartialEq)]
+    pub struct KittiesTest;
+    impl system::Trait for KittiesTest {
+        type Origin = Origin;
+        type Index = u64;
+        type BlockNumber = u64;
+        type Hash = H256;
+        type Hashing = BlakeTwo256;
+        type Digest = Digest;
+        type AccountId = u64;
+        type Lookup = IdentityLookup<Self::AccountId>;
+        type Header = Header;
+        type Event = ();
+        type Log = DigestItem;
+    }
+
+    impl balances::Trait for KittiesTest {
+        type Balance = u64;
+        type OnFreeBalanceZero = ();
+        type OnNewAccount = ();
+        type Event = ();
+        type TransactionPayment = ();
+        type TransferPayment = ();
+        type DustRemoval = ();
+    }
+
+    impl super::Trait for KittiesTest {
+        type Event = ();
+    }
+
+    type Kitties = super::Module<KittiesTest>;
+    type Balances = balances::Module<KittiesTest>;
+
+    fn build_ext() -> TestExternalities<Blake2Hasher> {
+        let mut t = system::GenesisConfig::<KittiesTest>::default().build_storage().unwrap().0;
+        t.extend(balances::GenesisConfig::<KittiesTest> {
+            balances: vec![(0, 1000), (1, 1000), (10, 1000), (20, 1000)],
+            ..Default::default()
+        }.build_storage().unwrap().0);
+        t.extend(GenesisConfig::<KittiesTest> {
+            kitties: vec![  (0, H256::from([1; 32]), H256::from([1; 32]), 50, 0),
+                            (1, H256::zero(), H256::zero(), 100, 1)],
+            auctions: vec![(H256::zero(), 10, 20)],
+            ..Default::default()
+        }.build_storage().unwrap().0);
+        t.into()
+    }
+
+    #[test]
+    fn should_build_genesis_kitties() {
+        with_externalities(&mut build_ext(), || {
+            let kitty0 = Kitties::kitty_by_index(0);
+            let kitty1 = Kitties::kitty_by_index(1);
+
+            // Check we have 2 kitties, as specified
+            assert_eq!(Kitties::all_kitties_count(), 2);
+
+            // Check that they are owned correctly
+            assert_eq!(Kitties::owner_of(kitty0), Some(0));
+            assert_eq!(Kitties::owner_of(kitty1), Some(1));
+
+            // Check owners own the correct amount of kitties
+            assert_eq!(Kitties::owned_kitty_count(0), 1);
+            assert_eq!(Kitties::owned_kitty_count(2), 0);
+
+            // Check that the price and generation come from the config
+            assert_eq!(Kitties::kitty(kitty1).price, 100);
+            assert_eq!(Kitties::kitty(kitty1).gen, 1);
+        })
+    }
+
+    #[test]
+    #[should_panic(expected = "genesis auctions must expire within the auction period limit")]
+    fn should_reject_genesis_auctions_past_the_limit() {
+        GenesisConfig::<KittiesTest> {
+            kitties: vec![(0, H256::zero(), H256::zero(), 0, 0)],
+            auctions: vec![(H256::zero(), 10, 17281)],
+            ..Default::default()
+        }.build_storage().unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "genesis marketplace fee and creator royalty exceed the price")]
+    fn should_reject_genesis_fees_above_the_price() {
+        GenesisConfig::<KittiesTest> {
+            marketplace_fee: Permill::from_percent(60),
+            creator_royalty: Permill::from_percent(50),
+            ..Default::default()
+        }.build_storage().unwrap();
+    }
+
+    #[test]
+    fn should_build_genesis_auctions() {
+        with_externalities(&mut build_ext(), || {
+            let auction = Kitties::auction_of(H256::zero()).expect("genesis auction is open");
+            assert_eq!(auction.kitty_owner, 1);
+            assert_eq!(auction.min_bid, 10);
+            assert_eq!(Kitties::auctions_expiring_count(20), 1);
+
+            // An auctioned kitty cannot be moved
+            assert_noop!(Kitties::transfer(Origin::signed(1), 2, H256::zero()), "This kitty has an open auction.");
+        })
+    }
+
+    #[test]
+    fn create_kitty_should_work() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+
+            assert_eq!(Kitties::all_kitties_count(), 3);
+            assert_eq!(Kitties::owned_kitty_count(10), 1);
+
+            let hash = Kitties::kitty_by_index(2);
+            assert_eq!(Kitties::owner_of(hash), Some(10));
+            assert_eq!(Kitties::kitty_of_owner_by_index((10, 0)), hash);
+        })
+    }
+
+    #[test]
+    fn breed_kitty_should_respect_ownership_and_cooldown() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let matron = Kitties::kitty_of_owner_by_index((10, 0));
+            let sire = Kitties::kitty_of_owner_by_index((10, 1));
+            let other = Kitties::kitty_by_index(0);
+
+            assert_noop!(Kitties::breed_kitty(Origin::signed(10), matron, matron), "A kitty cannot breed with itself");
+            assert_noop!(Kitties::breed_kitty(Origin::signed(10), matron, other),
+                "You do not own the sire and its owner has not approved siring");
+
+            assert_ok!(Kitties::breed_kitty(Origin::signed(10), matron, sire));
+            let child = Kitties::kitty_of_owner_by_index((10, 2));
+            assert_eq!(Kitties::parents_of(child), Some((matron, sire)));
+            assert_eq!(Kitties::kitty(child).gen, 1);
+            assert_eq!(Kitties::cooldown_end(matron), 10);
+
+            assert_noop!(Kitties::breed_kitty(Origin::signed(10), matron, sire), "The matron is still on breeding cooldown");
+            assert_noop!(Kitties::breed_kitty(Origin::signed(10), child, matron), "A kitty cannot breed with its parent or child");
+        })
+    }
+
+    #[test]
+    fn approved_sire_can_be_used_once() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let matron = Kitties::kitty_of_owner_by_index((10, 0));
+            let sire = Kitties::kitty_by_index(0);
+
+            assert_ok!(Kitties::approve_siring(Origin::signed(0), sire, 10));
+            assert_ok!(Kitties::breed_kitty(Origin::signed(10), matron, sire));
+
+            assert_eq!(Kitties::sire_allowed_to(sire), None);
+            assert_eq!(Kitties::owned_kitty_count(10), 2);
+        })
+    }
+
+    #[test]
+    fn siring_offer_should_work() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let matron = Kitties::kitty_of_owner_by_index((10, 0));
+            let sire = Kitties::kitty_by_index(0);
+
+            assert_ok!(Kitties::offer_siring(Origin::signed(0), sire, 100));
+            assert_noop!(Kitties::buy_siring(Origin::signed(10), sire, matron, 99), "The siring fee is more than your max fee");
+            assert_ok!(Kitties::buy_siring(Origin::signed(10), sire, matron, 100));
+
+            assert_eq!(Balances::free_balance(&10), 900);
+            assert_eq!(Balances::free_balance(&0), 1100);
+            assert_eq!(Kitties::owned_kitty_count(10), 2);
+
+            // The offer stays listed for other matrons
+            assert_eq!(Kitties::siring_offer_of(sire), Some(100));
+
+            // An auctioned kitty cannot be listed
+            assert_noop!(Kitties::offer_siring(Origin::signed(1), H256::zero(), 100), "This kitty has an open auction.");
+        })
+    }
+
+    #[test]
+    fn lineage_should_be_tracked_and_inbreeding_rejected() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let matron = Kitties::kitty_of_owner_by_index((10, 0));
+            let sire = Kitties::kitty_of_owner_by_index((10, 1));
+
+            assert_ok!(Kitties::breed_kitty(Origin::signed(10), matron, sire));
+            <system::Module<KittiesTest>>::set_block_number(10);
+            assert_ok!(Kitties::breed_kitty(Origin::signed(10), matron, sire));
+            let child_1 = Kitties::kitty_of_owner_by_index((10, 2));
+            let child_2 = Kitties::kitty_of_owner_by_index((10, 3));
+
+            assert_eq!(Kitties::children_of(matron), vec![child_1, child_2]);
+            assert_eq!(Kitties::ancestors(child_1, 2), vec![matron, sire]);
+            assert_eq!(Kitties::descendants(sire, 2), vec![child_1, child_2]);
+
+            assert_noop!(Kitties::breed_kitty(Origin::signed(10), child_1, child_2),
+                "These kitties share an ancestor within the inbreeding depth");
+        })
+    }
+
+    #[test]
+    fn burn_kitty_should_keep_indexes_consistent() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let first = Kitties::kitty_of_owner_by_index((10, 0));
+            let second = Kitties::kitty_of_owner_by_index((10, 1));
+            assert_ok!(Kitties::set_price(Origin::signed(10), first, 30));
+
+            assert_noop!(Kitties::burn_kitty(Origin::signed(20), first), "You do not own this kitty");
+            assert_ok!(Kitties::burn_kitty(Origin::signed(10), first));
+
+            assert_eq!(Kitties::owner_of(first), None);
+            assert_eq!(Kitties::kitty(first).price, 0);
+
+            assert_eq!(Kitties::owned_kitty_count(10), 1);
+            assert_eq!(Kitties::kitty_of_owner_by_index((10, 0)), second);
+
+            assert_eq!(Kitties::all_kitties_count(), 3);
+            assert_eq!(Kitties::kitty_by_index(2), second);
+
+            // A burned kitty can't breed
+            assert_eq!(Kitties::quote_breed(second, first).error, Some(b"The sire does not exist".to_vec()));
+
+            // A kitty with an open auction cannot be burned
+            assert_noop!(Kitties::burn_kitty(Origin::signed(1), H256::zero()), "This kitty has an open auction.");
+        })
+    }
+
+    #[test]
+    fn approved_accounts_and_operators_can_transfer() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let first = Kitties::kitty_of_owner_by_index((10, 0));
+            let second = Kitties::kitty_of_owner_by_index((10, 1));
+
+            assert_noop!(Kitties::transfer_from(Origin::signed(20), 10, 20, first), "You are not approved to transfer this kitty");
+
+            assert_ok!(Kitties::approve(Origin::signed(10), 20, first));
+            assert_ok!(Kitties::transfer_from(Origin::signed(20), 10, 1, first));
+            assert_eq!(Kitties::owner_of(first), Some(1));
+            // The approval is cleared by the transfer
+            assert_eq!(Kitties::approved_of(first), None);
+
+            assert_ok!(Kitties::set_approval_for_all(Origin::signed(10), 20, true));
+            assert_ok!(Kitties::transfer_from(Origin::signed(20), 10, 20, second));
+            assert_eq!(Kitties::owner_of(second), Some(20));
+
+            assert_ok!(Kitties::set_approval_for_all(Origin::signed(10), 20, false));
+            assert!(!Kitties::is_approved_for_all((10, 20)));
+        })
+    }
+
+    #[test]
+    fn kitties_should_not_be_transferred_to_their_owner() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let kitty_id = Kitties::kitty_of_owner_by_index((10, 0));
+            assert_ok!(Kitties::approve(Origin::signed(10), 20, kitty_id));
+
+            assert_noop!(Kitties::transfer(Origin::signed(10), 10, kitty_id), "You cannot transfer a kitty to yourself");
+            assert_noop!(Kitties::transfer_from(Origin::signed(20), 10, 10, kitty_id), "You cannot transfer a kitty to yourself");
+            assert_noop!(Kitties::batch_transfer(Origin::signed(10), vec![(10, kitty_id)]), "You cannot transfer a kitty to yourself");
+            assert_eq!(Kitties::owned_kitty_count(10), 1);
+        })
+    }
+
+    #[test]
+    fn batch_operations_should_be_atomic() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitties(Origin::signed(10), 3));
+            assert_eq!(Kitties::owned_kitty_count(10), 3);
+            assert_eq!(Kitties::all_kitties_count(), 5);
+
+            let first = Kitties::kitty_of_owner_by_index((10, 0));
+            let second = Kitties::kitty_of_owner_by_index((10, 1));
+            let not_owned = Kitties::kitty_by_index(0);
+
+            assert_noop!(Kitties::batch_transfer(Origin::signed(10), vec![(20, first), (20, not_owned)]),
+                "'from' account does not own this kitty");
+            assert_noop!(Kitties::batch_transfer(Origin::signed(10), vec![(20, first), (1, first)]),
+                "The batch contains the same kitty twice");
+
+            assert_ok!(Kitties::batch_transfer(Origin::signed(10), vec![(20, first), (1, second)]));
+            assert_eq!(Kitties::owner_of(first), Some(20));
+            assert_eq!(Kitties::owner_of(second), Some(1));
+            assert_eq!(Kitties::owned_kitty_count(10), 1);
+
+            assert_noop!(Kitties::batch_set_price(Origin::signed(20), vec![(first, 5), (second, 5)]), "You do not own this cat");
+            assert_ok!(Kitties::create_auction(Origin::signed(20), first, 10, 30));
+            assert_noop!(Kitties::batch_set_price(Origin::signed(20), vec![(first, 5)]), "This kitty has an open auction.");
+
+            assert_noop!(Kitties::create_kitties(Origin::signed(10), 33), "The batch is larger than the maximum batch size");
+        })
+    }
+
+    #[test]
+    fn offers_should_reserve_and_settle_funds() {
+        with_externalities(&mut build_ext(), || {
+            let kitty_id = Kitties::kitty_by_index(0);
+
+            assert_ok!(Kitties::make_offer(Origin::signed(10), kitty_id, 300, 5));
+            assert_eq!(Balances::reserved_balance(&10), 300);
+            assert_noop!(Kitties::make_offer(Origin::signed(10), kitty_id, 200, 5),
+                "You already have an offer for this cat, withdraw it first");
+
+            assert_ok!(Kitties::make_offer(Origin::signed(20), kitty_id, 100, 5));
+            assert_ok!(Kitties::withdraw_offer(Origin::signed(20), kitty_id));
+            assert_eq!(Balances::reserved_balance(&20), 0);
+
+            assert_ok!(Kitties::accept_offer(Origin::signed(0), kitty_id, 10));
+            assert_eq!(Kitties::owner_of(kitty_id), Some(10));
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::free_balance(&10), 700);
+            assert_eq!(Balances::free_balance(&0), 1300);
+            assert_eq!(Kitties::offer_of((kitty_id, 10)), None);
+        })
+    }
+
+    #[test]
+    fn owners_should_not_accept_their_own_offers() {
+        with_externalities(&mut build_ext(), || {
+            let kitty_id = Kitties::kitty_by_index(0);
+
+            assert_ok!(Kitties::make_offer(Origin::signed(10), kitty_id, 300, 5));
+            assert_ok!(Kitties::transfer(Origin::signed(0), 10, kitty_id));
+
+            assert_noop!(Kitties::accept_offer(Origin::signed(10), kitty_id, 10), "You can't accept your own offer");
+            assert_ok!(Kitties::withdraw_offer(Origin::signed(10), kitty_id));
+            assert_eq!(Balances::free_balance(&10), 1000);
+            assert_eq!(Kitties::owned_kitty_count(10), 1);
+        })
+    }
+
+    #[test]
+    fn expired_offers_should_be_unreserved() {
+        with_externalities(&mut build_ext(), || {
+            let kitty_id = Kitties::kitty_by_index(0);
+
+            assert_ok!(Kitties::make_offer(Origin::signed(10), kitty_id, 300, 5));
+
+            <system::Module<KittiesTest>>::set_block_number(5);
+            assert_noop!(Kitties::accept_offer(Origin::signed(0), kitty_id, 10), "This offer is expired.");
+            <Kitties as OnFinalize<u64>>::on_finalize(5);
+
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Kitties::offer_of((kitty_id, 10)), None);
+            assert!(Kitties::offers_expire_at(5).is_empty());
+        })
+    }
+
+    #[test]
+    fn sales_should_pay_fee_and_royalty() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::set_creator_royalty(Origin::ROOT, Permill::from_percent(5)));
+            assert_noop!(Kitties::set_marketplace_fee(Origin::ROOT, Permill::from_percent(96)),
+                "The marketplace fee and creator royalty exceed the price");
+            assert_ok!(Kitties::set_marketplace_fee(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(Kitties::set_fee_destination(Origin::ROOT, 1));
+
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let kitty_id = Kitties::kitty_of_owner_by_index((10, 0));
+            assert_eq!(Kitties::creator_of(kitty_id), Some(10));
+
+            // The creator selling their own kitty earns no royalty
+            assert_ok!(Kitties::set_price(Origin::signed(10), kitty_id, 100));
+            assert_ok!(Kitties::buy_kitty(Origin::signed(20), kitty_id, 100));
+            assert_eq!(Balances::free_balance(&20), 900);
+            assert_eq!(Balances::free_balance(&1), 1010);
+            assert_eq!(Balances::free_balance(&10), 1090);
+
+            assert_ok!(Kitties::set_price(Origin::signed(20), kitty_id, 200));
+            assert_ok!(Kitties::buy_kitty(Origin::signed(0), kitty_id, 200));
+            assert_eq!(Balances::free_balance(&0), 800);
+            assert_eq!(Balances::free_balance(&1), 1030);
+            assert_eq!(Balances::free_balance(&10), 1100);
+            assert_eq!(Balances::free_balance(&20), 1070);
+        })
+    }
+
+    #[test]
+    fn sales_should_pay_in_full_or_not_at_all() {
+        let mut t = system::GenesisConfig::<KittiesTest>::default().build_storage().unwrap().0;
+        t.extend(balances::GenesisConfig::<KittiesTest> {
+            balances: vec![(0, 1000), (1, 1000), (20, 1000), (40, 50)],
+            existential_deposit: 50,
+            ..Default::default()
+        }.build_storage().unwrap().0);
+        let mut ext: TestExternalities<Blake2Hasher> = t.into();
+        with_externalities(&mut ext, || {
+            assert_ok!(Kitties::set_marketplace_fee(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(Kitties::set_creator_royalty(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(Kitties::set_fee_destination(Origin::ROOT, 1));
+
+            // Account 30 has no balance, a royalty below the existential deposit can't reach it
+            assert_ok!(Kitties::create_kitty(Origin::signed(30)));
+            let kitty_id = Kitties::kitty_of_owner_by_index((30, 0));
+            assert_ok!(Kitties::transfer(Origin::signed(30), 20, kitty_id));
+            assert_ok!(Kitties::set_price(Origin::signed(20), kitty_id, 100));
+
+            assert_noop!(Kitties::buy_kitty(Origin::signed(0), kitty_id, 100),
+                "A share of this sale is too low to create the receiving account");
+            assert_eq!(Balances::free_balance(&0), 1000);
+            assert_eq!(Balances::free_balance(&1), 1000);
+
+            assert_ok!(Kitties::set_price(Origin::signed(20), kitty_id, 500));
+            assert_ok!(Kitties::buy_kitty(Origin::signed(0), kitty_id, 500));
+            assert_eq!(Balances::free_balance(&30), 50);
+            assert_eq!(Balances::free_balance(&1), 1050);
+            assert_eq!(Balances::free_balance(&20), 1400);
+
+            // Paying the fee first would wipe the rest of account 40's balance before the seller gets it
+            assert_ok!(Kitties::set_price(Origin::signed(0), kitty_id, 50));
+            assert_noop!(Kitties::buy_kitty(Origin::signed(40), kitty_id, 50),
+                "This purchase would leave your balance below the existential deposit");
+            assert_eq!(Balances::free_balance(&40), 50);
+            assert_eq!(Kitties::owner_of(kitty_id), Some(0));
+        })
+    }
+
+    #[test]
+    fn auctioned_kitties_should_not_be_bought() {
+        with_externalities(&mut build_ext(), || {
+            let kitty_id = Kitties::kitty_by_index(0);
+            assert_ok!(Kitties::set_price(Origin::signed(0), kitty_id, 50));
+            assert_ok!(Kitties::create_auction(Origin::signed(0), kitty_id, 10, 30));
+
+            assert_noop!(Kitties::buy_kitty(Origin::signed(10), kitty_id, 50), "This kitty has an open auction.");
+            assert_noop!(Kitties::set_price(Origin::signed(0), kitty_id, 60), "This kitty has an open auction.");
+            assert_eq!(Balances::free_balance(&10), 1000);
+        })
+    }
+
+    #[test]
+    fn dutch_auction_price_should_decline() {
+        with_externalities(&mut build_ext(), || {
+            let kitty_id = Kitties::kitty_by_index(0);
+
+            <system::Module<KittiesTest>>::set_block_number(1);
+            assert_noop!(Kitties::create_dutch_auction(Origin::signed(0), kitty_id, 100, 200, 10),
+                "The start price has to be at least the end price");
+            assert_ok!(Kitties::create_dutch_auction(Origin::signed(0), kitty_id, 200, 100, 10));
+
+            assert_noop!(Kitties::transfer(Origin::signed(0), 10, kitty_id), "This kitty has an open auction.");
+            assert_noop!(Kitties::bid_auction(Origin::signed(10), kitty_id, 300), "This auction does not take bids");
+
+            <system::Module<KittiesTest>>::set_block_number(6);
+            let auction = Kitties::auction_of(kitty_id).expect("dutch auction is open");
+            assert_eq!(Kitties::dutch_price(&auction, 6), Some(150));
+            assert_eq!(Kitties::dutch_price(&auction, 20), Some(100));
+
+            assert_ok!(Kitties::buy_dutch(Origin::signed(10), kitty_id));
+            assert_eq!(Kitties::owner_of(kitty_id), Some(10));
+            assert_eq!(Kitties::auction_of(kitty_id), None);
+            assert_eq!(Kitties::auctions_expiring_count(11), 0);
+            assert_eq!(Balances::free_balance(&10), 850);
+            assert_eq!(Balances::free_balance(&0), 1150);
+
+            // The price of the full balance range still declines without overflowing
+            assert_ok!(Kitties::create_dutch_auction(Origin::signed(10), kitty_id, u64::max_value(), 0, 10));
+            let auction = Kitties::auction_of(kitty_id).expect("dutch auction is open");
+            assert_eq!(Kitties::dutch_price(&auction, 11), Some(9_223_372_036_854_775_808));
+            assert_eq!(Kitties::dutch_price(&auction, 16), Some(0));
+
+            // An english auction without bids releases the kitty once it expires
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+            assert_eq!(Kitties::auction_of(H256::zero()), None);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(1));
+        })
+    }
+
+    #[test]
+    fn auction_reserve_price_should_be_enforced() {
+        with_externalities(&mut build_ext(), || {
+            assert_noop!(Kitties::set_auction_reserve(Origin::signed(10), H256::zero(), 100),
+                "You can't set a reserve for an auction you don't own");
+            assert_ok!(Kitties::set_auction_reserve(Origin::signed(1), H256::zero(), 100));
+
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 50));
+            assert_noop!(Kitties::set_auction_reserve(Origin::signed(1), H256::zero(), 10),
+                "The reserve price can only be set before the first bid");
+
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(1));
+            assert_eq!(Kitties::auction_of(H256::zero()), None);
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::free_balance(&10), 1000);
+        })
+    }
+
+    #[test]
+    fn cancel_auction_should_refund_bids() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let kitty_id = Kitties::kitty_of_owner_by_index((10, 0));
+
+            assert_ok!(Kitties::create_auction(Origin::signed(10), kitty_id, 10, 30));
+            assert_noop!(Kitties::cancel_auction(Origin::signed(20), kitty_id), "You can't cancel an auction you don't own");
+            assert_ok!(Kitties::cancel_auction(Origin::signed(10), kitty_id));
+            assert_eq!(Balances::free_balance(&10), 1000);
+
+            assert_ok!(Kitties::create_auction(Origin::signed(10), kitty_id, 10, 30));
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), kitty_id, 100));
+            assert_eq!(Balances::reserved_balance(&20), 100);
+
+            // Once there are bids the owner pays a penalty to the high bidder
+            assert_ok!(Kitties::cancel_auction(Origin::signed(10), kitty_id));
+            assert_eq!(Balances::reserved_balance(&20), 0);
+            assert_eq!(Balances::free_balance(&20), 1005);
+            assert_eq!(Balances::free_balance(&10), 995);
+            assert_eq!(Kitties::auction_of(kitty_id), None);
+            assert_eq!(Kitties::auctions_expiring_count(30), 0);
+
+            assert_ok!(Kitties::transfer(Origin::signed(10), 20, kitty_id));
+        })
+    }
+
+    #[test]
+    fn late_bids_should_extend_the_auction() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 20));
+            assert_eq!(Kitties::auction_of(H256::zero()).map(|auction| auction.expiry), Some(20));
+
+            <system::Module<KittiesTest>>::set_block_number(16);
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 30));
+            assert_eq!(Kitties::auction_of(H256::zero()).map(|auction| auction.expiry), Some(25));
+            assert_eq!(Kitties::auctions_expiring_count(20), 0);
+            assert_eq!(Kitties::auctions_expiring_count(25), 1);
+
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(1));
+            // The outbid account takes its bid back whenever it wants
+            assert_eq!(Balances::reserved_balance(&20), 20);
+            assert_noop!(Kitties::withdraw_bid(Origin::signed(10), H256::zero()), "You can't withdraw the highest bid");
+            assert_ok!(Kitties::withdraw_bid(Origin::signed(20), H256::zero()));
+            assert_eq!(Balances::reserved_balance(&20), 0);
+            assert_noop!(Kitties::withdraw_bid(Origin::signed(20), H256::zero()), "You have no bid for this cat");
+
+            <system::Module<KittiesTest>>::set_block_number(25);
+            <Kitties as OnFinalize<u64>>::on_finalize(25);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(10));
+        })
+    }
+
+    #[test]
+    fn bids_should_respect_the_minimum_increment() {
+        with_externalities(&mut build_ext(), || {
+            assert_noop!(Kitties::set_bid_increment(Origin::signed(1), 10, Permill::zero()), "bad origin: expected to be a root origin");
+            assert_ok!(Kitties::set_bid_increment(Origin::ROOT, 10, Permill::zero()));
+
+            // The first bid only has to beat the minimum bid
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 11));
+
+            assert_noop!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 20),
+                "Your bid has to exceed the highest bid by at least the minimum increment.");
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 21));
+
+            assert_ok!(Kitties::set_bid_increment(Origin::ROOT, 1, Permill::from_percent(50)));
+            assert_noop!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 30),
+                "Your bid has to exceed the highest bid by at least the minimum increment.");
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 31));
+        })
+    }
+
+    #[test]
+    fn settlement_should_carry_over_to_later_blocks() {
+        with_externalities(&mut build_ext(), || {
+            let auctions = MAX_SETTLEMENTS_PER_BLOCK + 2;
+            assert_ok!(Kitties::create_kitties(Origin::signed(10), auctions));
+            for i in 0..u64::from(auctions) {
+                let kitty_id = Kitties::kitty_of_owner_by_index((10, i));
+                assert_ok!(Kitties::create_auction(Origin::signed(10), kitty_id, 10, 30));
+                assert_ok!(Kitties::bid_auction(Origin::signed(20), kitty_id, 20));
+            }
+            assert_eq!(Kitties::auctions_expiring_count(30), auctions);
+
+            <system::Module<KittiesTest>>::set_block_number(30);
+            <Kitties as OnFinalize<u64>>::on_finalize(30);
+            assert_eq!(Kitties::auctions_expiring_count(30), 2);
+            assert_eq!(Kitties::owned_kitty_count(20), u64::from(MAX_SETTLEMENTS_PER_BLOCK));
+
+            // Expired auctions waiting for settlement take no more bids
+            let pending = Kitties::auction_expiring_by_index((30, 0));
+            assert_noop!(Kitties::bid_auction(Origin::signed(1), pending, 50), "This auction is expired.");
+
+            <system::Module<KittiesTest>>::set_block_number(31);
+            <Kitties as OnFinalize<u64>>::on_finalize(31);
+            assert_eq!(Kitties::auctions_expiring_count(30), 0);
+            assert_eq!(Kitties::owned_kitty_count(20), u64::from(auctions));
+            assert_eq!(Balances::free_balance(&20), 1000 - 20 * u64::from(auctions));
+        })
+    }
+
+    #[test]
+    fn outbid_bids_should_be_topped_up_or_withdrawn() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 20));
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 30));
+
+            // Bidding again only reserves the difference
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 40));
+            assert_eq!(Balances::reserved_balance(&20), 40);
+
+            assert_ok!(Kitties::withdraw_bid(Origin::signed(10), H256::zero()));
+            assert_eq!(Balances::free_balance(&10), 1000);
+
+            // Only the top-up has to be free, the rest of the bid is reserved already
+            assert_ok!(<Balances as Currency<_>>::transfer(&20, &30, 900));
+            assert_eq!(Balances::free_balance(&20), 60);
+            assert_noop!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 110),
+                "You don't have enough free balance for this bid");
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 80));
+            assert_eq!(Balances::reserved_balance(&20), 80);
+            assert_eq!(Balances::free_balance(&20), 20);
+
+            // Settlement only touches the winning bid
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(20));
+            assert_eq!(Balances::reserved_balance(&20), 0);
+            assert_eq!(Balances::free_balance(&20), 20);
+        })
+    }
+
+    #[test]
+    fn stale_bids_should_not_carry_into_the_next_auction() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 30));
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 40));
+
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(20));
+            assert_eq!(Balances::reserved_balance(&10), 30);
+
+            // The bid left from the last auction is released, not topped up
+            assert_ok!(Kitties::create_auction(Origin::signed(20), H256::zero(), 5, 40));
+            assert_ok!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 10));
+            assert_eq!(Balances::reserved_balance(&10), 10);
+            assert_eq!(Balances::free_balance(&10), 990);
+            assert_noop!(Kitties::withdraw_bid(Origin::signed(10), H256::zero()), "You can't withdraw the highest bid");
+
+            <system::Module<KittiesTest>>::set_block_number(40);
+            <Kitties as OnFinalize<u64>>::on_finalize(40);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(10));
+            assert_eq!(Balances::reserved_balance(&10), 0);
+            assert_eq!(Balances::free_balance(&10), 990);
+        })
+    }
+
+    #[test]
+    fn failed_settlement_should_be_retried_then_abandoned() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 50));
+            let _ = Balances::slash_reserved(&20, 10);
+
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(1));
+            assert_eq!(Kitties::settlement_attempts(H256::zero()), 1);
+            assert_eq!(Kitties::auctions_expiring_count(20 + SETTLEMENT_RETRY_DELAY), 1);
+
+            // The auction keeps its expiry and takes no bids while waiting for the retry
+            <system::Module<KittiesTest>>::set_block_number(25);
+            assert_noop!(Kitties::bid_auction(Origin::signed(10), H256::zero(), 100), "This auction is expired.");
+
+            for attempt in 1..MAX_SETTLEMENT_ATTEMPTS {
+                let block = 20 + SETTLEMENT_RETRY_DELAY * u64::from(attempt);
+                <system::Module<KittiesTest>>::set_block_number(block);
+                <Kitties as OnFinalize<u64>>::on_finalize(block);
+            }
+
+            assert_eq!(Kitties::auction_of(H256::zero()), None);
+            assert_eq!(Kitties::owner_of(H256::zero()), Some(1));
+            assert_eq!(Kitties::settlement_attempts(H256::zero()), 0);
+            assert_eq!(Balances::reserved_balance(&20), 0);
+            assert_eq!(Balances::free_balance(&20), 990);
+        })
+    }
+
+    #[test]
+    fn sealed_vickrey_auction_should_work() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let kitty_id = Kitties::kitty_of_owner_by_index((10, 0));
+            assert_ok!(Kitties::create_sealed_auction(Origin::signed(10), kitty_id, 10, 5, 5, true));
+
+            let salt = |n: u8| H256::from([n; 32]);
+            assert_ok!(Kitties::commit_bid(Origin::signed(20), kitty_id, Kitties::sealed_commitment(100, salt(1)), 150));
+            assert_ok!(Kitties::commit_bid(Origin::signed(0), kitty_id, Kitties::sealed_commitment(60, salt(2)), 60));
+            assert_ok!(Kitties::commit_bid(Origin::signed(1), kitty_id, Kitties::sealed_commitment(80, salt(3)), 90));
+            assert_noop!(Kitties::bid_auction(Origin::signed(20), kitty_id, 100), "This auction does not take bids");
+            assert_noop!(Kitties::reveal_bid(Origin::signed(20), kitty_id, 100, salt(1)), "The reveal phase of this auction has not started");
+
+            <system::Module<KittiesTest>>::set_block_number(5);
+            <Kitties as OnFinalize<u64>>::on_finalize(5);
+            assert_eq!(Kitties::auctions_expiring_count(10), 1);
+            assert_noop!(Kitties::commit_bid(Origin::signed(1), kitty_id, salt(4), 10), "The commit phase of this auction is over");
+
+            assert_noop!(Kitties::reveal_bid(Origin::signed(20), kitty_id, 100, salt(2)), "The bid and salt don't match your commitment");
+            assert_ok!(Kitties::reveal_bid(Origin::signed(20), kitty_id, 100, salt(1)));
+            assert_ok!(Kitties::reveal_bid(Origin::signed(0), kitty_id, 60, salt(2)));
+
+            assert_ok!(Kitties::withdraw_sealed_bid(Origin::signed(0), kitty_id));
+            assert_eq!(Balances::reserved_balance(&0), 0);
+            assert_noop!(Kitties::withdraw_sealed_bid(Origin::signed(1), kitty_id), "Your bid can still be revealed");
+
+            // The winner pays the second highest bid
+            <system::Module<KittiesTest>>::set_block_number(10);
+            <Kitties as OnFinalize<u64>>::on_finalize(10);
+            assert_eq!(Kitties::owner_of(kitty_id), Some(20));
+            assert_eq!(Balances::reserved_balance(&20), 0);
+            assert_eq!(Balances::free_balance(&20), 940);
+            assert_eq!(Balances::free_balance(&10), 1060);
+
+            // Unrevealed bids lose part of their deposit
+            assert_ok!(Kitties::withdraw_sealed_bid(Origin::signed(1), kitty_id));
+            assert_eq!(Balances::reserved_balance(&1), 0);
+            assert_eq!(Balances::free_balance(&1), 955);
+        })
+    }
+
+    #[test]
+    fn stale_sealed_bids_should_not_carry_into_the_next_auction() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitty(Origin::signed(10)));
+            let kitty_id = Kitties::kitty_of_owner_by_index((10, 0));
+            assert_ok!(Kitties::create_sealed_auction(Origin::signed(10), kitty_id, 10, 5, 5, false));
+
+            let salt = |n: u8| H256::from([n; 32]);
+            assert_ok!(Kitties::commit_bid(Origin::signed(20), kitty_id, Kitties::sealed_commitment(50, salt(1)), 50));
+            assert_ok!(Kitties::commit_bid(Origin::signed(0), kitty_id, Kitties::sealed_commitment(60, salt(2)), 60));
+            assert_ok!(Kitties::commit_bid(Origin::signed(1), kitty_id, Kitties::sealed_commitment(80, salt(3)), 90));
+
+            <system::Module<KittiesTest>>::set_block_number(5);
+            <Kitties as OnFinalize<u64>>::on_finalize(5);
+            assert_ok!(Kitties::reveal_bid(Origin::signed(20), kitty_id, 50, salt(1)));
+            <system::Module<KittiesTest>>::set_block_number(10);
+            <Kitties as OnFinalize<u64>>::on_finalize(10);
+            assert_eq!(Kitties::owner_of(kitty_id), Some(20));
+
+            // The unrevealed commitments belong to the last auction, whose reveal phase is over
+            assert_ok!(Kitties::create_sealed_auction(Origin::signed(20), kitty_id, 10, 5, 5, false));
+            assert_ok!(Kitties::withdraw_sealed_bid(Origin::signed(0), kitty_id));
+            assert_eq!(Balances::reserved_balance(&0), 0);
+            assert_eq!(Balances::free_balance(&0), 970);
+
+            assert_ok!(Kitties::commit_bid(Origin::signed(1), kitty_id, Kitties::sealed_commitment(30, salt(4)), 30));
+            assert_eq!(Balances::reserved_balance(&1), 30);
+            assert_eq!(Balances::free_balance(&1), 925);
+            assert_noop!(Kitties::commit_bid(Origin::signed(1), kitty_id, salt(5), 30), "You already committed a bid for this cat");
+        })
+    }
+
+    #[test]
+    fn auction_indexes_should_track_sellers_and_bidders() {
+        with_externalities(&mut build_ext(), || {
+            assert_eq!(Kitties::active_auctions().len(), 1);
+            assert_eq!(Kitties::auctions_by_seller(&1).len(), 1);
+            assert_eq!(Kitties::auction_slots(), vec![(H256::zero(), 20, 0)]);
+
+            assert_ok!(Kitties::create_kitties(Origin::signed(10), 2));
+            let first = Kitties::kitty_of_owner_by_index((10, 0));
+            let second = Kitties::kitty_of_owner_by_index((10, 1));
+            assert_ok!(Kitties::create_auction(Origin::signed(10), first, 10, 30));
+            assert_ok!(Kitties::create_auction(Origin::signed(10), second, 10, 30));
+            assert_eq!(Kitties::active_auctions().len(), 3);
+            assert_eq!(Kitties::auctions_by_seller(&10).len(), 2);
+
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), first, 20));
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 15));
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), H256::zero(), 16));
+            assert_eq!(Kitties::auctions_by_bidder(&20), vec![(first, 20), (H256::zero(), 16)]);
+
+            assert_ok!(Kitties::cancel_auction(Origin::signed(10), first));
+            assert_eq!(Kitties::auctions_by_bidder(&20), vec![(H256::zero(), 16)]);
+            assert_eq!(Kitties::auctions_by_seller(&10).iter().map(|auction| auction.kitty_id).collect::<Vec<_>>(), vec![second]);
+            assert_eq!(Kitties::active_auctions().len(), 2);
+
+            <system::Module<KittiesTest>>::set_block_number(20);
+            <Kitties as OnFinalize<u64>>::on_finalize(20);
+            assert!(Kitties::auctions_by_bidder(&20).is_empty());
+            assert!(Kitties::auctions_by_seller(&1).is_empty());
+            assert_eq!(Kitties::active_auctions().len(), 1);
+        })
+    }
+
+    #[test]
+    fn lot_auction_should_settle_every_kitty() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::set_creator_royalty(Origin::ROOT, Permill::from_percent(10)));
+            assert_ok!(Kitties::create_kitties(Origin::signed(10), 3));
+            assert_ok!(Kitties::create_kitty(Origin::signed(0)));
+            let minted_by_0 = Kitties::kitty_of_owner_by_index((0, 1));
+            assert_ok!(Kitties::transfer(Origin::signed(0), 10, minted_by_0));
+            let lot: Vec<H256> = (0..4).map(|i| Kitties::kitty_of_owner_by_index((10, i))).collect();
+
+            assert_noop!(Kitties::create_lot_auction(Origin::signed(10), vec![lot[0], H256::zero()], 10, 30),
+                "You can't set an auction for a cat you don't own");
+            assert_ok!(Kitties::create_lot_auction(Origin::signed(10), lot.clone(), 10, 30));
+
+            // Every kitty of the lot is locked
+            assert_eq!(Kitties::lot_leader_of(lot[1]), Some(lot[0]));
+            assert_noop!(Kitties::transfer(Origin::signed(10), 20, lot[1]), "This kitty has an open auction.");
+            assert_noop!(Kitties::burn_kitty(Origin::signed(10), lot[2]), "This kitty has an open auction.");
+            assert_noop!(Kitties::create_auction(Origin::signed(10), lot[2], 10, 30), "This kitty has an open auction.");
+
+            assert_ok!(Kitties::bid_auction(Origin::signed(20), lot[0], 400));
+            let _ = Balances::slash_reserved(&20, 10);
+
+            // A failed settlement leaves the lot with the seller and still locked
+            <system::Module<KittiesTest>>::set_block_number(30);
+            <Kitties as OnFinalize<u64>>::on_finalize(30);
+            assert_eq!(Kitties::owned_kitty_count(10), 4);
+            assert_eq!(Kitties::lot_leader_of(lot[2]), Some(lot[0]));
+
+            assert_ok!(Balances::reserve(&20, 10));
+            let retry_at = 30 + SETTLEMENT_RETRY_DELAY;
+            <system::Module<KittiesTest>>::set_block_number(retry_at);
+            <Kitties as OnFinalize<u64>>::on_finalize(retry_at);
+
+            assert!(lot.iter().all(|kitty_id| Kitties::owner_of(kitty_id) == Some(20)));
+            assert!(lot.iter().all(|kitty_id| Kitties::lot_leader_of(kitty_id).is_none()));
+            assert_eq!(Kitties::owned_kitty_count(10), 0);
+
+            // Only the kitty account 0 created pays it a royalty, on a quarter of the price
+            assert_eq!(Balances::free_balance(&0), 1010);
+            assert_eq!(Balances::free_balance(&10), 1390);
+        })
+    }
+
+    #[test]
+    fn kitty_queries_should_page_and_quote() {
+        with_externalities(&mut build_ext(), || {
+            assert_ok!(Kitties::create_kitties(Origin::signed(10), 3));
+            let owned: Vec<H256> = (0..3).map(|i| Kitties::kitty_of_owner_by_index((10, i))).collect();
+
+            assert_eq!(Kitties::kitties_page(0, 2), (vec![Kitties::kitty_by_index(0), Kitties::kitty_by_index(1)], 5));
+            assert_eq!(Kitties::kitties_page(4, 2).0.len(), 1);
+            assert_eq!(Kitties::owned_kitties_page(&10, 1, 10), (owned[1..].to_vec(), 3));
+            assert_eq!(Kitties::owned_kitties_page(&10, 5, 10), (vec![], 3));
+
+            let info = Kitties::kitty_info(H256::zero()).unwrap();
+            assert_eq!(info.owner, 1);
+            assert!(info.in_auction);
+            assert_eq!(Kitties::kitty_info(H256::from([1; 32])).unwrap().traits, vec![1; 5]);
+            assert_eq!(Kitties::auction_for(H256::zero()).map(|auction| auction.kitty_owner), Some(1));
+            assert!(Kitties::kitty_info(H256::from([9; 32])).is_none());
+
+            let quote = Kitties::quote_breed(owned[0], owned[1]);
+            assert_eq!(quote.error, None);
+            assert_eq!(quote.child_gen, 1);
+            assert_ok!(Kitties::breed_kitty(Origin::signed(10), owned[0], owned[1]));
+            assert_eq!(Kitties::quote_breed(owned[0], owned[2]).error,
+                Some(b"The matron is still on breeding cooldown".to_vec()));
+        })
     }
-}
\ No newline at end of file
+}
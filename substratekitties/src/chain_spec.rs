@@ -1,14 +1,17 @@
-use primitives::{ed25519, sr25519, Pair};
+use primitives::{ed25519, sr25519, Pair, blake2_256, crypto::Ss58Codec};
+use parity_codec::Encode;
+use std::env;
 use substratekitties_runtime::{
-	AccountId, GenesisConfig, ConsensusConfig, TimestampConfig, BalancesConfig,
-	SudoConfig, IndicesConfig,
+	AccountId, Balance, Hash, GenesisConfig, ConsensusConfig, TimestampConfig, BalancesConfig,
+	SudoConfig, IndicesConfig, SubstratekittiesConfig, Permill,
 };
 use substrate_service;
+use substrate_telemetry::TelemetryEndpoints;
 
 use ed25519::Public as AuthorityId;
 
-// Note this is the URL for the telemetry server
-//const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
+/// Balance given to endowed accounts that don't name one.
+const DEFAULT_ENDOWMENT: Balance = 1 << 60;
 
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
 pub type ChainSpec = substrate_service::ChainSpec<GenesisConfig>;
@@ -22,6 +25,9 @@ pub enum Alternative {
 	Development,
 	/// Whatever the current runtime is, with simple Alice/Bob auths.
 	LocalTestnet,
+	/// Whatever the current runtime is, with authorities, sudo key, endowments and fees read
+	/// from `KITTIES_STAGING_*` environment variables.
+	Staging,
 }
 
 fn authority_key(s: &str) -> AuthorityId {
@@ -40,6 +46,22 @@ impl Alternative {
 	/// Get an actual chain config from one of the alternatives.
 	pub(crate) fn load(self) -> Result<ChainSpec, String> {
 		Ok(match self {
+			Alternative::Staging => {
+				// Read once here so a bad environment fails now rather than when the genesis is built
+				let config = StagingConfig::from_env()?;
+				let telemetry = config.telemetry_url
+					.map(|url| TelemetryEndpoints::new(vec![(url, 0)]));
+				ChainSpec::from_genesis(
+					"Staging Testnet",
+					"staging_testnet",
+					staging_genesis,
+					vec![],
+					telemetry,
+					None,
+					None,
+					None
+				)
+			},
 			Alternative::Development => ChainSpec::from_genesis(
 				"Development",
 				"dev",
@@ -85,12 +107,144 @@ impl Alternative {
 		match s {
 			"dev" => Some(Alternative::Development),
 			"" | "local" => Some(Alternative::LocalTestnet),
+			"staging" => Some(Alternative::Staging),
 			_ => None,
 		}
 	}
 }
 
+/// Fees and block timing set at genesis.
+#[derive(Clone, Debug)]
+struct FeeSchedule {
+	transaction_base_fee: Balance,
+	transaction_byte_fee: Balance,
+	existential_deposit: Balance,
+	/// Half the target block time, in seconds.
+	minimum_period: u64,
+}
+
+impl Default for FeeSchedule {
+	fn default() -> Self {
+		FeeSchedule {
+			transaction_base_fee: 1,
+			transaction_byte_fee: 0,
+			existential_deposit: 500,
+			minimum_period: 5, // 10 second block time.
+		}
+	}
+}
+
+/// Settings of the staging chain.
+///
+/// * `KITTIES_STAGING_AUTHORITIES`: comma separated authority keys. Required.
+/// * `KITTIES_STAGING_SUDO`: the sudo key, which also receives marketplace fees. Required.
+/// * `KITTIES_STAGING_ENDOWED`: comma separated `account[=balance]`. The sudo key is endowed
+///   with `DEFAULT_ENDOWMENT` when it is not listed, so fees below the existential deposit can
+///   be paid to it.
+/// * `KITTIES_STAGING_GENESIS_KITTIES`: `true` to give every endowed account a genesis kitty.
+///   Defaults to `false`.
+/// * `KITTIES_STAGING_BASE_FEE`, `KITTIES_STAGING_BYTE_FEE`, `KITTIES_STAGING_EXISTENTIAL_DEPOSIT`
+///   and `KITTIES_STAGING_MINIMUM_PERIOD`: override the `FeeSchedule` defaults.
+/// * `KITTIES_STAGING_TELEMETRY_URL`: the telemetry server to report to. No telemetry is sent
+///   unless it is set.
+///
+/// Keys are either SS58 addresses or secret URIs such as `//Alice`.
+#[derive(Clone, Debug)]
+struct StagingConfig {
+	authorities: Vec<AuthorityId>,
+	root_key: AccountId,
+	endowed: Vec<(AccountId, Balance)>,
+	genesis_kitties: bool,
+	fees: FeeSchedule,
+	telemetry_url: Option<String>,
+}
+
+impl StagingConfig {
+	fn from_env() -> Result<Self, String> {
+		let authorities = required_var("KITTIES_STAGING_AUTHORITIES")?
+			.split(',')
+			.map(|s| parse_key::<ed25519::Pair>(s.trim()))
+			.collect::<Result<Vec<_>, _>>()?;
+		if authorities.is_empty() {
+			return Err("KITTIES_STAGING_AUTHORITIES needs at least one authority".into());
+		}
+
+		let root_key = parse_key::<sr25519::Pair>(required_var("KITTIES_STAGING_SUDO")?.trim())?;
+
+		let mut endowed = match env::var("KITTIES_STAGING_ENDOWED") {
+			Ok(list) => list.split(',').map(|entry| {
+				let mut parts = entry.trim().splitn(2, '=');
+				let account = parse_key::<sr25519::Pair>(parts.next().unwrap_or_default())?;
+				let balance: Balance = match parts.next() {
+					Some(b) => b.parse().map_err(|_| format!("Invalid balance `{}` in KITTIES_STAGING_ENDOWED", b))?,
+					None => DEFAULT_ENDOWMENT,
+				};
+				Ok((account, balance))
+			}).collect::<Result<Vec<_>, String>>()?,
+			Err(_) => vec![],
+		};
+		if !endowed.iter().any(|(account, _)| *account == root_key) {
+			endowed.push((root_key.clone(), DEFAULT_ENDOWMENT));
+		}
+		let genesis_kitties = optional_var("KITTIES_STAGING_GENESIS_KITTIES", false)?;
+
+		let defaults = FeeSchedule::default();
+		let fees = FeeSchedule {
+			transaction_base_fee: optional_var("KITTIES_STAGING_BASE_FEE", defaults.transaction_base_fee)?,
+			transaction_byte_fee: optional_var("KITTIES_STAGING_BYTE_FEE", defaults.transaction_byte_fee)?,
+			existential_deposit: optional_var("KITTIES_STAGING_EXISTENTIAL_DEPOSIT", defaults.existential_deposit)?,
+			minimum_period: optional_var("KITTIES_STAGING_MINIMUM_PERIOD", defaults.minimum_period)?,
+		};
+
+		let telemetry_url = env::var("KITTIES_STAGING_TELEMETRY_URL").ok().filter(|url| !url.is_empty());
+
+		Ok(StagingConfig { authorities, root_key, endowed, genesis_kitties, fees, telemetry_url })
+	}
+}
+
+fn required_var(name: &str) -> Result<String, String> {
+	env::var(name).map_err(|_| format!("{} must be set for the staging chain", name))
+}
+
+fn optional_var<V: std::str::FromStr>(name: &str, default: V) -> Result<V, String> {
+	match env::var(name) {
+		Ok(v) => v.trim().parse().map_err(|_| format!("Invalid value `{}` for {}", v, name)),
+		Err(_) => Ok(default),
+	}
+}
+
+/// Reads a public key given as an SS58 address or derives it from a secret URI.
+fn parse_key<P: Pair>(s: &str) -> Result<P::Public, String> where P::Public: Ss58Codec {
+	P::Public::from_ss58check(s)
+		.or_else(|_| P::from_string(s, None).map(|pair| pair.public()))
+		.map_err(|e| format!("`{}` is neither an SS58 address nor a secret URI: {:?}", s, e))
+}
+
 fn testnet_genesis(initial_authorities: Vec<AuthorityId>, endowed_accounts: Vec<AccountId>, root_key: AccountId) -> GenesisConfig {
+	genesis(
+		initial_authorities,
+		endowed_accounts.into_iter().map(|k| (k, DEFAULT_ENDOWMENT)).collect(),
+		root_key,
+		true,
+		&FeeSchedule::default(),
+	)
+}
+
+/// `ChainSpec::from_genesis` only takes a plain `fn`, so the environment is read again here.
+fn staging_genesis() -> GenesisConfig {
+	let config = StagingConfig::from_env().expect("the environment is checked when the spec is loaded; qed");
+	genesis(config.authorities, config.endowed, config.root_key, config.genesis_kitties, &config.fees)
+}
+
+fn genesis(
+	initial_authorities: Vec<AuthorityId>,
+	endowed: Vec<(AccountId, Balance)>,
+	root_key: AccountId,
+	genesis_kitties: bool,
+	fees: &FeeSchedule,
+) -> GenesisConfig {
+	let endowed_accounts: Vec<AccountId> = endowed.iter().map(|(k, _)| k.clone()).collect();
+	let kitties = if genesis_kitties { endowed_accounts.iter().map(genesis_kitty).collect() } else { vec![] };
 	GenesisConfig {
 		consensus: Some(ConsensusConfig {
 			code: include_bytes!("../runtime/wasm/target/wasm32-unknown-unknown/release/substratekitties_runtime_wasm.compact.wasm").to_vec(),
@@ -98,22 +252,49 @@ fn testnet_genesis(initial_authorities: Vec<AuthorityId>, endowed_accounts: Vec<
 		}),
 		system: None,
 		timestamp: Some(TimestampConfig {
-			minimum_period: 5, // 10 second block time.
+			minimum_period: fees.minimum_period,
 		}),
 		indices: Some(IndicesConfig {
 			ids: endowed_accounts.clone(),
 		}),
 		balances: Some(BalancesConfig {
-			transaction_base_fee: 1,
-			transaction_byte_fee: 0,
-			existential_deposit: 500,
+			transaction_base_fee: fees.transaction_base_fee,
+			transaction_byte_fee: fees.transaction_byte_fee,
+			existential_deposit: fees.existential_deposit,
 			transfer_fee: 0,
 			creation_fee: 0,
-			balances: endowed_accounts.iter().cloned().map(|k|(k, 1 << 60)).collect(),
+			balances: endowed,
 			vesting: vec![],
 		}),
 		sudo: Some(SudoConfig {
-			key: root_key,
+			key: root_key.clone(),
+		}),
+		substratekitties: Some(SubstratekittiesConfig {
+			kitties,
+			auctions: vec![],
+			auction_period_limit: 17280,
+			predefined_auction_period_limit: 20,
+			auction_extension_window: 10,
+			auction_extension_period: 10,
+			min_bid_increment: 1,
+			min_bid_increment_permill: Permill::from_percent(5),
+			auction_cancel_penalty: Permill::from_percent(5),
+			unrevealed_forfeit: Permill::from_percent(50),
+			mutation_rate: Permill::from_percent(1),
+			breeding_cooldown: 10,
+			inbreeding_depth: 1,
+			max_batch_size: 32,
+			offer_period_limit: 17280,
+			marketplace_fee: Permill::from_percent(2),
+			creator_royalty: Permill::from_percent(1),
+			fee_destination: root_key,
 		}),
 	}
 }
+
+/// A generation 0 kitty, not for sale, whose id and dna are derived from its owner.
+fn genesis_kitty(owner: &AccountId) -> (AccountId, Hash, Hash, Balance, u64) {
+	let id: Hash = ("kitty", owner).using_encoded(blake2_256).into();
+	let dna: Hash = ("dna", owner).using_encoded(blake2_256).into();
+	(owner.clone(), id, dna, 0, 0)
+}
@@ -1,23 +1,172 @@
 use crate::service;
 use futures::{future, Future, sync::oneshot};
 use std::cell::RefCell;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use tokio::runtime::Runtime;
 pub use substrate_cli::{VersionInfo, IntoExit, error};
-use substrate_cli::{informant, parse_and_execute, NoCustom};
+use substrate_cli::{informant, parse_and_execute, impl_augment_clap, GetLogFilter};
 use substrate_service::{ServiceFactory, Roles as ServiceRoles};
 use crate::chain_spec;
+use crate::inspect::KittyStorage;
+use crate::dev_seal::DevSeal;
 use std::ops::Deref;
+use structopt::StructOpt;
 use log::info;
 
+/// Options of the `run` command specific to substratekitties.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KittiesParams {
+	/// Port of the HTTP server for the `kitties_*` RPC methods. It listens on the same interface
+	/// as `--rpc-port` and is off whenever the node's HTTP RPC is.
+	#[structopt(long = "kitties-rpc-port", value_name = "PORT", default_value = "9935")]
+	kitties_rpc_port: u16,
+
+	/// Do not serve the `kitties_*` RPC methods.
+	#[structopt(long = "no-kitties-rpc")]
+	no_kitties_rpc: bool,
+
+	/// Author a block as soon as a transaction enters the pool, or when `dev_sealBlock` is called,
+	/// instead of running Aura. Only allowed on the `dev` chain.
+	#[structopt(long = "dev-seal")]
+	dev_seal: bool,
+
+	/// Port of the HTTP server for `dev_sealBlock`. It only listens on localhost, whatever
+	/// `--rpc-external` says.
+	#[structopt(long = "dev-seal-port", value_name = "PORT", default_value = "9936")]
+	dev_seal_port: u16,
+}
+
+impl_augment_clap!(KittiesParams);
+
+/// Subcommands specific to substratekitties.
+#[derive(Debug, StructOpt, Clone)]
+pub enum CustomSubcommands {
+	/// Inspect kitties in the database of a stopped node.
+	#[structopt(name = "kitties")]
+	Kitties(KittiesCmd),
+	/// Inspect auctions in the database of a stopped node.
+	#[structopt(name = "auctions")]
+	Auctions(AuctionsCmd),
+}
+
+impl GetLogFilter for CustomSubcommands {
+	fn get_log_filter(&self) -> Option<String> {
+		None
+	}
+}
+
+/// The database read by `kitties` and `auctions`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct DatabaseParams {
+	/// The chain whose database is read: `dev`, `local`, `staging` or a chain spec file.
+	#[structopt(long = "chain", default_value = "local")]
+	chain: String,
+
+	/// The base path the node was started with.
+	#[structopt(long = "base-path", short = "d", parse(from_os_str))]
+	base_path: PathBuf,
+
+	/// The block number or hash to read at. Defaults to the best block.
+	#[structopt(long = "at")]
+	at: Option<String>,
+}
+
+impl DatabaseParams {
+	fn open(self) -> error::Result<KittyStorage> {
+		KittyStorage::open(&self.chain, self.base_path, self.at.as_ref().map(String::as_str))
+	}
+}
+
+/// The `kitties` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct KittiesCmd {
+	#[structopt(flatten)]
+	db: DatabaseParams,
+
+	#[structopt(subcommand)]
+	action: KittiesAction,
+}
+
+/// Subcommands of `kitties`.
+#[derive(Debug, StructOpt, Clone)]
+pub enum KittiesAction {
+	/// List every kitty with its owner.
+	#[structopt(name = "list")]
+	List,
+	/// Show a kitty, its lineage and any auction it is in.
+	#[structopt(name = "show")]
+	Show {
+		/// The kitty id, as a hex hash.
+		id: String,
+	},
+	/// List the kitties of an account.
+	#[structopt(name = "owner")]
+	Owner {
+		/// The owner, as an SS58 address.
+		account: String,
+	},
+}
+
+impl KittiesCmd {
+	fn run(self) -> error::Result<()> {
+		let storage = self.db.open()?;
+		match self.action {
+			KittiesAction::List => storage.list_kitties(),
+			KittiesAction::Show { id } => storage.show_kitty(&id),
+			KittiesAction::Owner { account } => storage.kitties_of_owner(&account),
+		}
+	}
+}
+
+/// The `auctions` command.
+#[derive(Debug, StructOpt, Clone)]
+pub struct AuctionsCmd {
+	#[structopt(flatten)]
+	db: DatabaseParams,
+
+	#[structopt(subcommand)]
+	action: AuctionsAction,
+}
+
+/// Subcommands of `auctions`.
+#[derive(Debug, StructOpt, Clone)]
+pub enum AuctionsAction {
+	/// List open auctions by the block they expire in.
+	#[structopt(name = "pending")]
+	Pending,
+}
+
+impl AuctionsCmd {
+	fn run(self) -> error::Result<()> {
+		let storage = self.db.open()?;
+		match self.action {
+			AuctionsAction::Pending => storage.pending_auctions(),
+		}
+	}
+}
+
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()> where
 	I: IntoIterator<Item = T>,
 	T: Into<std::ffi::OsString> + Clone,
 	E: IntoExit,
 {
-	parse_and_execute::<service::Factory, NoCustom, NoCustom, _, _, _, _, _>(
+	let custom = parse_and_execute::<service::Factory, CustomSubcommands, KittiesParams, _, _, _, _, _>(
 		load_spec, &version, "substrate-node", args, exit,
-	 	|exit, _custom_args, config| {
+	 	|exit, params: KittiesParams, mut config| {
+			if !params.no_kitties_rpc {
+				config.custom.kitties_rpc = config.rpc_http
+					.map(|addr| SocketAddr::new(addr.ip(), params.kitties_rpc_port));
+				config.custom.kitties_rpc_cors = config.rpc_cors.clone();
+			}
+			if params.dev_seal {
+				if config.chain_spec.id() != "dev" {
+					return Err(format!("--dev-seal is only allowed on the dev chain, not `{}`", config.chain_spec.id()).into());
+				}
+				config.custom.dev_seal = Some(DevSeal::default());
+				config.custom.dev_seal_rpc = Some(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), params.dev_seal_port));
+			}
 			info!("{}", version.name);
 			info!("  version {}", config.full_version());
 			info!("  by {}, 2019", version.author);
@@ -39,13 +188,21 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 				),
 			}.map_err(|e| format!("{:?}", e))
 		}
-	).map_err(Into::into).map(|_| ())
+	)?;
+
+	match custom {
+		Some(CustomSubcommands::Kitties(cmd)) => cmd.run(),
+		Some(CustomSubcommands::Auctions(cmd)) => cmd.run(),
+		None => Ok(()),
+	}
 }
 
-fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
+/// Resolves `--chain`: a built-in chain name, or else the path of a JSON chain spec. Both the
+/// runtime config and the raw storage genesis written by `build-spec [--raw]` are accepted.
+pub(crate) fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
 	Ok(match chain_spec::Alternative::from(id) {
 		Some(spec) => Some(spec.load()?),
-		None => None,
+		None => Some(chain_spec::ChainSpec::from_json_file(PathBuf::from(id))?),
 	})
 }
 
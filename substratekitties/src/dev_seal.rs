@@ -0,0 +1,129 @@
+//! `--dev-seal`: authors a block whenever a transaction enters the pool or `dev_sealBlock` is
+//! called, instead of waiting for Aura slots.
+//!
+//! Blocks are imported straight into the client without an Aura seal, so a dev-seal chain can't
+//! be synced by Aura nodes. Each block's timestamp is at least one slot after its parent's, which
+//! keeps the timestamp and Aura modules happy but lets the chain's clock run ahead of the wall
+//! clock when blocks are sealed quickly.
+
+use std::cmp;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use futures::{Future, IntoFuture, Stream, sync::{mpsc, oneshot}};
+use log::{info, warn};
+use parking_lot::Mutex;
+use basic_authorship::ProposerFactory;
+use consensus::SlotDuration;
+use consensus_common::{BlockImport, BlockOrigin, Environment, ForkChoiceStrategy, ImportBlock, Proposer};
+use inherents::{InherentData, InherentIdentifier};
+use runtime_primitives::{generic::BlockId, traits::{Block as BlockT, Header as HeaderT, ProvideRuntimeApi}};
+use substrate_service::{FullClient, FullComponents, ServiceFactory};
+use substratekitties_runtime::{kitties_api::DevSealApi, Hash};
+use crate::service::Factory;
+
+/// Identifier of the timestamp inherent of `srml-timestamp`.
+const TIMESTAMP_INHERENT: InherentIdentifier = *b"timstap0";
+
+/// How long the proposer may spend filling a block.
+const PROPOSE_DURATION: Duration = Duration::from_secs(1);
+
+type Author = ProposerFactory<FullClient<Factory>, <Factory as ServiceFactory>::FullTransactionPoolApi>;
+
+/// Asks the author for a block, and optionally for the hash of the block it sealed.
+pub struct SealRequest {
+	reply: Option<oneshot::Sender<Result<Hash, String>>>,
+}
+
+impl SealRequest {
+	/// A request whose outcome is sent back on `reply`.
+	pub fn new(reply: oneshot::Sender<Result<Hash, String>>) -> Self {
+		SealRequest { reply: Some(reply) }
+	}
+}
+
+/// Carries seal requests from the RPC server to the author.
+pub struct DevSeal {
+	sender: mpsc::UnboundedSender<SealRequest>,
+	receiver: Mutex<Option<mpsc::UnboundedReceiver<SealRequest>>>,
+}
+
+impl Default for DevSeal {
+	fn default() -> Self {
+		let (sender, receiver) = mpsc::unbounded();
+		DevSeal { sender, receiver: Mutex::new(Some(receiver)) }
+	}
+}
+
+impl DevSeal {
+	/// Where `dev_sealBlock` sends its requests.
+	pub fn requests(&self) -> mpsc::UnboundedSender<SealRequest> {
+		self.sender.clone()
+	}
+}
+
+/// The task that authors blocks for `service`. It must be spawned once.
+pub fn start(
+	service: &FullComponents<Factory>,
+	seal: &DevSeal,
+) -> Result<impl Future<Item = (), Error = ()> + Send, String> {
+	let requests = seal.receiver.lock().take().ok_or("The dev-seal author is already running")?;
+	let client = service.client();
+	let slot_duration = SlotDuration::get_or_compute(&*client).map_err(|e| format!("{:?}", e))?.get();
+	let author: Author = ProposerFactory {
+		client: client.clone(),
+		transaction_pool: service.transaction_pool(),
+		inherents_pool: service.inherents_pool(),
+	};
+
+	let pool_events = service.transaction_pool()
+		.import_notification_stream()
+		.map(|_| SealRequest { reply: None });
+
+	Ok(pool_events.select(requests).for_each(move |request| {
+		let result = seal_block(&client, &author, slot_duration);
+		match &result {
+			Ok(hash) => info!("Sealed block {}", hash),
+			Err(e) => warn!("Failed to seal a block: {}", e),
+		}
+		if let Some(reply) = request.reply {
+			let _ = reply.send(result);
+		}
+		Ok(())
+	}))
+}
+
+fn seal_block(client: &Arc<FullClient<Factory>>, author: &Author, slot_duration: u64) -> Result<Hash, String> {
+	let best_hash = client.info().map_err(|e| format!("{:?}", e))?.chain.best_hash;
+	let parent = client.header(&BlockId::Hash(best_hash))
+		.map_err(|e| format!("{:?}", e))?
+		.ok_or("The best block has no header")?;
+
+	let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| format!("{:?}", e))?.as_secs();
+	let parent_timestamp = client.runtime_api().timestamp(&BlockId::Hash(best_hash)).map_err(|e| format!("{:?}", e))?;
+	let timestamp = cmp::max(now, parent_timestamp + slot_duration);
+	let mut inherent_data = InherentData::new();
+	inherent_data.put_data(TIMESTAMP_INHERENT, &timestamp).map_err(|e| format!("{:?}", e))?;
+
+	let block = author.init(&parent, &[])
+		.map_err(|e| format!("{:?}", e))?
+		.propose(inherent_data, PROPOSE_DURATION)
+		.into_future()
+		.wait()
+		.map_err(|e| format!("{:?}", e))?;
+
+	let (header, body) = block.deconstruct();
+	let hash = header.hash();
+	let import = ImportBlock {
+		origin: BlockOrigin::Own,
+		header,
+		justification: None,
+		post_digests: vec![],
+		body: Some(body),
+		finalized: false,
+		auxiliary: vec![],
+		fork_choice: ForkChoiceStrategy::LongestChain,
+	};
+	client.import_block(import, None).map_err(|e| format!("{:?}", e))?;
+
+	Ok(hash)
+}
@@ -0,0 +1,144 @@
+//! Offline inspection of kitty and auction storage in the node's database.
+//!
+//! Storage is read through the `KittiesApi` and `KittyAuctionsApi` runtime APIs, so the commands
+//! follow the runtime's storage layout instead of rebuilding its keys.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use primitives::crypto::Ss58Codec;
+use runtime_primitives::traits::ProvideRuntimeApi;
+use substrate_client::runtime_api::BlockId;
+use substrate_service::{FactoryFullConfiguration, FullClient};
+use substratekitties_runtime::{
+	kitties_api::{KittiesApi, KittyAuctionsApi}, opaque::Block, AccountId, Auction, Balance, BlockNumber, Hash,
+};
+use crate::service;
+use crate::cli::{error, load_spec};
+
+type KittyAuction = Auction<Hash, Balance, BlockNumber, AccountId>;
+
+/// Reads the kitties module's storage at one block.
+pub struct KittyStorage {
+	client: Arc<FullClient<service::Factory>>,
+	at: BlockId<Block>,
+}
+
+impl KittyStorage {
+	/// Opens the database of `chain` under `base_path`, the same layout `--base-path` uses.
+	/// `at` is a block number or hash and defaults to the best block.
+	pub fn open(chain: &str, base_path: PathBuf, at: Option<&str>) -> error::Result<Self> {
+		let spec = load_spec(chain)?
+			.ok_or_else(|| format!("Unknown chain `{}`", chain))?;
+		let mut config = FactoryFullConfiguration::<service::Factory>::default_with_spec(spec);
+		config.database_path = base_path
+			.join("chains")
+			.join(config.chain_spec.id())
+			.join("db")
+			.to_string_lossy()
+			.into();
+		let client = substrate_service::new_client::<service::Factory>(&config)
+			.map_err(|e| format!("{:?}", e))?;
+
+		let at = match at {
+			Some(s) => match s.parse::<BlockNumber>() {
+				Ok(number) => BlockId::Number(number),
+				Err(_) => BlockId::Hash(parse_hash(s)?),
+			},
+			None => BlockId::Hash(client.info().map_err(|e| format!("{:?}", e))?.chain.best_hash),
+		};
+
+		Ok(KittyStorage { client, at })
+	}
+
+	fn kitty_summary(&self, id: Hash) -> error::Result<String> {
+		let kitty = self.client.runtime_api().kitty(&self.at, id).map_err(|e| format!("{:?}", e))?;
+		Ok(format!(
+			"{:?} owner {}{}",
+			id,
+			kitty.as_ref().map(|k| k.owner.to_ss58check()).unwrap_or_else(|| "none".into()),
+			if kitty.as_ref().map_or(false, |k| k.in_auction) { " (in auction)" } else { "" },
+		))
+	}
+
+	/// `kitties list`: every kitty in `AllKittiesArray` order.
+	pub fn list_kitties(&self) -> error::Result<()> {
+		let (ids, count) = self.client.runtime_api()
+			.all_kitties(&self.at, 0, u64::max_value())
+			.map_err(|e| format!("{:?}", e))?;
+		println!("{} kitties", count);
+		for (i, id) in ids.into_iter().enumerate() {
+			println!("{:>6}  {}", i, self.kitty_summary(id)?);
+		}
+		Ok(())
+	}
+
+	/// `kitties show`: one kitty with its owner, lineage and any open auction.
+	pub fn show_kitty(&self, id: &str) -> error::Result<()> {
+		let id = parse_hash(id)?;
+		let api = self.client.runtime_api();
+		let info = api.kitty(&self.at, id)
+			.map_err(|e| format!("{:?}", e))?
+			.ok_or_else(|| format!("No kitty {:?}", id))?;
+
+		println!("{:#?}", info.kitty);
+		println!("owner:        {}", info.owner.to_ss58check());
+		println!("creator:      {}", info.creator.map(|o| o.to_ss58check()).unwrap_or_else(|| "unknown".into()));
+		match info.parents {
+			Some((matron, sire)) => println!("parents:      {:?} x {:?}", matron, sire),
+			None => println!("parents:      none"),
+		}
+		println!("children:     {}", info.children);
+		println!("cooldown end: {}", info.cooldown_end);
+		println!("traits:       {:?}", info.traits);
+
+		if let Some(auction) = api.auction(&self.at, id).map_err(|e| format!("{:?}", e))? {
+			println!("auction:      {:#?}", auction);
+		}
+		Ok(())
+	}
+
+	/// `kitties owner`: the kitties in `OwnedKittiesArray` of `account`.
+	pub fn kitties_of_owner(&self, account: &str) -> error::Result<()> {
+		let account = AccountId::from_ss58check(account)
+			.map_err(|e| format!("Invalid account `{}`: {:?}", account, e))?;
+		let (ids, count) = self.client.runtime_api()
+			.kitties_owned_by(&self.at, account.clone(), 0, u64::max_value())
+			.map_err(|e| format!("{:?}", e))?;
+		println!("{} owns {} kitties", account.to_ss58check(), count);
+		for (i, id) in ids.into_iter().enumerate() {
+			println!("{:>6}  {}", i, self.kitty_summary(id)?);
+		}
+		Ok(())
+	}
+
+	/// `auctions pending`: open auctions grouped by the `AuctionsExpiring` bucket they settle in.
+	pub fn pending_auctions(&self) -> error::Result<()> {
+		let api = self.client.runtime_api();
+		let slots = api.auction_slots(&self.at).map_err(|e| format!("{:?}", e))?;
+		let count = slots.len();
+		let mut buckets: BTreeMap<BlockNumber, Vec<(u32, Hash, KittyAuction)>> = BTreeMap::new();
+		for (id, block, index) in slots {
+			let auction = api.auction(&self.at, id)
+				.map_err(|e| format!("{:?}", e))?
+				.ok_or_else(|| format!("Auction of {:?} is scheduled but missing", id))?;
+			buckets.entry(block).or_insert_with(Vec::new).push((index, id, auction));
+		}
+
+		println!("{} open auctions in {} buckets", count, buckets.len());
+		for (block, mut auctions) in buckets {
+			let size = api.auctions_expiring_count(&self.at, block).map_err(|e| format!("{:?}", e))?;
+			println!("block {} ({} scheduled)", block, size);
+			auctions.sort_by_key(|(index, _, _)| *index);
+			for (index, id, auction) in auctions {
+				println!("{:>6}  {:?} {:?}", index, id, auction);
+			}
+		}
+		Ok(())
+	}
+}
+
+fn parse_hash(s: &str) -> error::Result<Hash> {
+	let hex = if s.starts_with("0x") { &s[2..] } else { s };
+	hex.parse::<Hash>().map_err(|_| format!("Invalid hash `{}`", s).into())
+}
@@ -6,6 +6,9 @@
 mod chain_spec;
 mod service;
 mod cli;
+mod dev_seal;
+mod inspect;
+mod rpc;
 
 pub use substrate_cli::{VersionInfo, IntoExit, error};
 
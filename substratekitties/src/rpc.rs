@@ -0,0 +1,217 @@
+//! `kitties_*` JSON-RPC methods backed by the `KittiesApi` runtime API, and `dev_sealBlock`.
+//!
+//! The v1.0 service has no way to add methods to its own RPC servers, so these are served by
+//! separate HTTP servers next to them. `dev_sealBlock` gets a server of its own that only listens
+//! on localhost.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use futures::{Future, sync::{mpsc, oneshot}};
+use jsonrpc_core::{Error, ErrorCode, IoHandler, Result};
+use jsonrpc_derive::rpc;
+use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation, ServerBuilder};
+use log::info;
+use serde::Serialize;
+use runtime_primitives::{generic::BlockId, traits::ProvideRuntimeApi};
+use substrate_service::FullClient;
+use substratekitties_runtime::{
+	balance_string, kitties_api::KittiesApi, opaque::Block, AccountId, Auction, Balance, BlockNumber, Hash, KittyInfo,
+};
+use crate::service;
+use crate::dev_seal::SealRequest;
+
+/// Kitties returned per page by `kitties_ownedBy` and `kitties_allKitties`.
+const PAGE_SIZE: u64 = 50;
+
+type Kitty = KittyInfo<AccountId, Hash, Balance, BlockNumber>;
+
+/// One page of a kitty listing.
+#[derive(Serialize)]
+pub struct Page<T> {
+	pub page: u64,
+	pub page_size: u64,
+	/// Number of items over all pages.
+	pub total: u64,
+	pub items: Vec<T>,
+}
+
+/// What breeding two kitties would result in if it happened at the best block. Balances are
+/// decimal strings, like in the runtime types.
+#[derive(Serialize)]
+pub struct BreedQuote {
+	pub can_breed: bool,
+	/// Why the kitties can't breed, if they can't.
+	pub reason: Option<String>,
+	/// The fee `buy_siring` charges when the sire is offered for siring.
+	#[serde(serialize_with = "balance_string::option::serialize")]
+	pub siring_fee: Option<Balance>,
+	pub child_gen: u64,
+	/// Blocks the matron would have to wait before breeding again.
+	pub matron_cooldown: BlockNumber,
+	/// Blocks the sire would have to wait before breeding again.
+	pub sire_cooldown: BlockNumber,
+}
+
+/// Kitty queries, answered at the best block.
+#[rpc]
+pub trait KittiesRpc {
+	/// A kitty with its owner and breeding state.
+	#[rpc(name = "kitties_getKitty")]
+	fn get_kitty(&self, id: Hash) -> Result<Option<Kitty>>;
+
+	/// A page of `owner`'s kitties, the first page when `page` is omitted.
+	#[rpc(name = "kitties_ownedBy")]
+	fn owned_by(&self, owner: AccountId, page: Option<u64>) -> Result<Page<Kitty>>;
+
+	/// A page of all kitties, the first page when `page` is omitted.
+	#[rpc(name = "kitties_allKitties")]
+	fn all_kitties(&self, page: Option<u64>) -> Result<Page<Kitty>>;
+
+	/// The auction a kitty is sold in, on its own or as part of a lot.
+	#[rpc(name = "kitties_auction")]
+	fn auction(&self, id: Hash) -> Result<Option<Auction<Hash, Balance, BlockNumber, AccountId>>>;
+
+	/// What breeding `matron` with `sire` would result in now.
+	#[rpc(name = "kitties_quoteBreed")]
+	fn quote_breed(&self, matron: Hash, sire: Hash) -> Result<BreedQuote>;
+}
+
+/// Implements `KittiesRpc` on top of a full client.
+pub struct Kitties {
+	client: Arc<FullClient<service::Factory>>,
+}
+
+impl Kitties {
+	fn best_block(&self) -> Result<BlockId<Block>> {
+		let info = self.client.info().map_err(internal_error)?;
+		Ok(BlockId::Hash(info.chain.best_hash))
+	}
+
+	/// Resolves the ids of one page into kitties. The ids and kitties are read at the same block,
+	/// so an id without a kitty means the indexes are broken and is reported as an error rather
+	/// than returning a short page.
+	fn page(&self, at: &BlockId<Block>, page: u64, (ids, total): (Vec<Hash>, u64)) -> Result<Page<Kitty>> {
+		let api = self.client.runtime_api();
+		let mut items = Vec::with_capacity(ids.len());
+		for id in ids {
+			let kitty = api.kitty(at, id)
+				.map_err(internal_error)?
+				.ok_or_else(|| internal_error(format!("Kitty {:?} is indexed but does not exist", id)))?;
+			items.push(kitty);
+		}
+		Ok(Page { page, page_size: PAGE_SIZE, total, items })
+	}
+}
+
+impl KittiesRpc for Kitties {
+	fn get_kitty(&self, id: Hash) -> Result<Option<Kitty>> {
+		let at = self.best_block()?;
+		self.client.runtime_api().kitty(&at, id).map_err(internal_error)
+	}
+
+	fn owned_by(&self, owner: AccountId, page: Option<u64>) -> Result<Page<Kitty>> {
+		let at = self.best_block()?;
+		let page = page.unwrap_or_default();
+		let ids = self.client.runtime_api()
+			.kitties_owned_by(&at, owner, page.saturating_mul(PAGE_SIZE), PAGE_SIZE)
+			.map_err(internal_error)?;
+		self.page(&at, page, ids)
+	}
+
+	fn all_kitties(&self, page: Option<u64>) -> Result<Page<Kitty>> {
+		let at = self.best_block()?;
+		let page = page.unwrap_or_default();
+		let ids = self.client.runtime_api()
+			.all_kitties(&at, page.saturating_mul(PAGE_SIZE), PAGE_SIZE)
+			.map_err(internal_error)?;
+		self.page(&at, page, ids)
+	}
+
+	fn auction(&self, id: Hash) -> Result<Option<Auction<Hash, Balance, BlockNumber, AccountId>>> {
+		let at = self.best_block()?;
+		self.client.runtime_api().auction(&at, id).map_err(internal_error)
+	}
+
+	fn quote_breed(&self, matron: Hash, sire: Hash) -> Result<BreedQuote> {
+		let at = self.best_block()?;
+		let quote = self.client.runtime_api().quote_breed(&at, matron, sire).map_err(internal_error)?;
+		Ok(BreedQuote {
+			can_breed: quote.error.is_none(),
+			reason: quote.error.map(|e| String::from_utf8_lossy(&e).into_owned()),
+			siring_fee: quote.siring_fee,
+			child_gen: quote.child_gen,
+			matron_cooldown: quote.matron_cooldown,
+			sire_cooldown: quote.sire_cooldown,
+		})
+	}
+}
+
+/// Block authoring on demand, served on localhost when the node runs with `--dev-seal`.
+#[rpc]
+pub trait DevSealRpc {
+	/// Seals a block with the ready transactions and returns its hash.
+	#[rpc(name = "dev_sealBlock")]
+	fn seal_block(&self) -> Result<Hash>;
+}
+
+/// Implements `DevSealRpc` by forwarding requests to the dev-seal author.
+pub struct DevSeal {
+	requests: mpsc::UnboundedSender<SealRequest>,
+}
+
+impl DevSealRpc for DevSeal {
+	fn seal_block(&self) -> Result<Hash> {
+		let (reply, response) = oneshot::channel();
+		self.requests.unbounded_send(SealRequest::new(reply))
+			.map_err(|_| internal_error("The dev-seal author is not running"))?;
+		response.wait()
+			.map_err(|_| internal_error("The dev-seal author stopped"))?
+			.map_err(internal_error)
+	}
+}
+
+fn internal_error<E: std::fmt::Display>(e: E) -> Error {
+	Error {
+		code: ErrorCode::InternalError,
+		message: e.to_string(),
+		data: None,
+	}
+}
+
+/// Serves the `kitties_*` methods over HTTP on `addr` until the process exits.
+///
+/// `cors` is `--rpc-cors` and is applied the way the node's own HTTP server does: only those
+/// origins are allowed, and when it is set the `Host` header has to name the listening address.
+pub fn start_http(
+	addr: &SocketAddr,
+	client: Arc<FullClient<service::Factory>>,
+	cors: Option<&Vec<String>>,
+) -> std::io::Result<()> {
+	let mut io = IoHandler::new();
+	io.extend_with(Kitties { client }.to_delegate());
+	let origins: Option<Vec<AccessControlAllowOrigin>> = cors
+		.map(|origins| origins.iter().map(|origin| origin.as_str().into()).collect());
+	let hosts = if cors.is_some() { DomainsValidation::AllowOnly(vec![]) } else { DomainsValidation::Disabled };
+	let server = ServerBuilder::new(io)
+		.cors(origins.into())
+		.allowed_hosts(hosts)
+		.start_http(addr)?;
+	info!("Kitties RPC listening on http://{}", addr);
+	thread::spawn(move || server.wait());
+	Ok(())
+}
+
+/// Serves `dev_sealBlock` over HTTP on `addr` until the process exits. Anyone who reaches it can
+/// author blocks, so `addr` should be a loopback address, and requests have to name it in their
+/// `Host` header.
+pub fn start_dev_seal_http(addr: &SocketAddr, requests: mpsc::UnboundedSender<SealRequest>) -> std::io::Result<()> {
+	let mut io = IoHandler::new();
+	io.extend_with(DevSeal { requests }.to_delegate());
+	let server = ServerBuilder::new(io)
+		.allowed_hosts(DomainsValidation::AllowOnly(vec![]))
+		.start_http(addr)?;
+	info!("Dev-seal RPC listening on http://{}", addr);
+	thread::spawn(move || server.wait());
+	Ok(())
+}
@@ -2,6 +2,7 @@
 
 #![warn(unused_extern_crates)]
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 use log::info;
 use transaction_pool::{self, txpool::{Pool as TransactionPool}};
@@ -19,6 +20,7 @@ use inherents::InherentDataProviders;
 use network::construct_simple_protocol;
 use substrate_executor::native_executor_instance;
 use substrate_service::construct_service_factory;
+use crate::{rpc, dev_seal::{self, DevSeal}};
 
 pub use substrate_executor::NativeExecutor;
 // Our native executor instance.
@@ -32,6 +34,14 @@ native_executor_instance!(
 #[derive(Default)]
 pub struct NodeConfig {
 	inherent_data_providers: InherentDataProviders,
+	/// Where full nodes serve the `kitties_*` RPC methods, if at all.
+	pub kitties_rpc: Option<SocketAddr>,
+	/// `--rpc-cors`, applied to the kitties RPC server too.
+	pub kitties_rpc_cors: Option<Vec<String>>,
+	/// Set by `--dev-seal` to author blocks on demand instead of running Aura.
+	pub dev_seal: Option<DevSeal>,
+	/// Where `dev_sealBlock` is served when `dev_seal` is set, always a loopback address.
+	pub dev_seal_rpc: Option<SocketAddr>,
 }
 
 construct_simple_protocol! {
@@ -52,11 +62,30 @@ construct_service_factory! {
 		Genesis = GenesisConfig,
 		Configuration = NodeConfig,
 		FullService = FullComponents<Self>
-			{ |config: FactoryFullConfiguration<Self>, executor: TaskExecutor|
-				FullComponents::<Factory>::new(config, executor)
-			},
+			{ |config: FactoryFullConfiguration<Self>, executor: TaskExecutor| {
+				let kitties_rpc = config.custom.kitties_rpc;
+				let cors = config.custom.kitties_rpc_cors.clone();
+				let seal_requests = config.custom.dev_seal.as_ref().map(DevSeal::requests);
+				let seal_rpc = config.custom.dev_seal_rpc;
+				let service = FullComponents::<Factory>::new(config, executor)?;
+				if let Some(addr) = kitties_rpc {
+					rpc::start_http(&addr, service.client(), cors.as_ref())
+						.map_err(|e| format!("Failed to start the kitties RPC server on {}: {}", addr, e))?;
+				}
+				if let (Some(addr), Some(requests)) = (seal_rpc, seal_requests) {
+					rpc::start_dev_seal_http(&addr, requests)
+						.map_err(|e| format!("Failed to start the dev-seal RPC server on {}: {}", addr, e))?;
+				}
+				Ok(service)
+			}},
 		AuthoritySetup = {
 			|service: Self::FullService, executor: TaskExecutor, key: Option<Arc<Pair>>| {
+				if let Some(seal) = service.config.custom.dev_seal.as_ref() {
+					info!("Sealing blocks on demand instead of running Aura");
+					executor.spawn(dev_seal::start(&service, seal)?);
+					return Ok(service);
+				}
+
 				if let Some(key) = key {
 					info!("Using authority key {}", key.public());
 					let proposer = Arc::new(ProposerFactory {